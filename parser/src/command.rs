@@ -2,10 +2,12 @@ use crate::error::Error;
 use crate::ignore_block::IgnoreBlocks;
 use crate::token::Tokenizer;
 use regex::Regex;
+use std::fmt;
 
 pub mod assign;
 pub mod close;
 pub mod glacier;
+pub mod help;
 pub mod nominate;
 pub mod note;
 pub mod ping;
@@ -13,19 +15,99 @@ pub mod prioritize;
 pub mod relabel;
 pub mod second;
 pub mod shortcut;
+pub mod unknown;
 
-#[derive(Debug, PartialEq)]
-pub enum Command<'a> {
-    Relabel(Result<relabel::RelabelCommand, Error<'a>>),
-    Assign(Result<assign::AssignCommand, Error<'a>>),
-    Ping(Result<ping::PingCommand, Error<'a>>),
-    Nominate(Result<nominate::NominateCommand, Error<'a>>),
-    Prioritize(Result<prioritize::PrioritizeCommand, Error<'a>>),
-    Second(Result<second::SecondCommand, Error<'a>>),
-    Glacier(Result<glacier::GlacierCommand, Error<'a>>),
-    Shortcut(Result<shortcut::ShortcutCommand, Error<'a>>),
-    Close(Result<close::CloseCommand, Error<'a>>),
-    Note(Result<note::NoteCommand, Error<'a>>),
+/// Declares the commands: a variant of [`Command`] for each, named after its
+/// module, and the parser tried for it. Adding a command means adding a
+/// module with its parser and `KEYWORDS`, and listing it here.
+macro_rules! commands {
+    ($($variant:ident($module:ident::$command:ident),)*) => {
+        #[derive(Debug, PartialEq)]
+        pub enum Command<'a> {
+            $($variant(Result<$module::$command, Error<'a>>),)*
+            /// A word which is not a command, but is close to one.
+            Unknown(Result<unknown::UnknownCommand, Error<'a>>),
+            /// More than one command matched the same text. The source of the
+            /// error is an [`AmbiguousCommand`].
+            Ambiguous(Error<'a>),
+        }
+
+        /// The parsers tried on the text following each mention of the bot.
+        const PARSERS: &[CommandParser] = &[$(
+            CommandParser {
+                keywords: $module::KEYWORDS,
+                parse: |tok| parse_single_command($module::$command::parse, Command::$variant, tok),
+            },
+        )*];
+
+        impl<'a> Command<'a> {
+            pub fn is_ok(&self) -> bool {
+                match self {
+                    $(Command::$variant(r) => r.is_ok(),)*
+                    Command::Unknown(r) => r.is_ok(),
+                    Command::Ambiguous(_) => false,
+                }
+            }
+
+            /// A short name for the kind of command.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Command::$variant(_) => stringify!($module),)*
+                    Command::Unknown(_) => "unknown",
+                    Command::Ambiguous(_) => "ambiguous",
+                }
+            }
+        }
+
+        $(impl CommandKind for $module::$command {
+            fn take(command: Command<'_>) -> Result<Result<Self, Error<'_>>, Command<'_>> {
+                match command {
+                    Command::$variant(command) => Ok(command),
+                    command => Err(command),
+                }
+            }
+        })*
+    };
+}
+
+/// One kind of command, i.e. the contents of one variant of [`Command`].
+pub trait CommandKind: Sized {
+    /// Takes the command out of `command`, or gives it back if it is of
+    /// another kind.
+    fn take(command: Command<'_>) -> Result<Result<Self, Error<'_>>, Command<'_>>;
+}
+
+commands! {
+    Relabel(relabel::RelabelCommand),
+    Assign(assign::AssignCommand),
+    Ping(ping::PingCommand),
+    Nominate(nominate::NominateCommand),
+    Prioritize(prioritize::PrioritizeCommand),
+    Second(second::SecondCommand),
+    Glacier(glacier::GlacierCommand),
+    Shortcut(shortcut::ShortcutCommand),
+    Close(close::CloseCommand),
+    Note(note::NoteCommand),
+    Help(help::HelpCommand),
+}
+
+/// The error for text which more than one command parser accepted.
+#[derive(PartialEq, Eq, Debug)]
+pub struct AmbiguousCommand {
+    /// The names of the commands which matched.
+    pub commands: Vec<&'static str>,
+}
+
+impl std::error::Error for AmbiguousCommand {}
+
+impl fmt::Display for AmbiguousCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ambiguous command, it could be any of: {}",
+            self.commands.join(", ")
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -58,6 +140,39 @@ where
     }
 }
 
+type Parser = for<'a> fn(&Tokenizer<'a>) -> Option<(Tokenizer<'a>, Command<'a>)>;
+
+/// A parser for one kind of command.
+struct CommandParser {
+    /// The words which start the command, for recognizing misspelled ones.
+    keywords: &'static [&'static str],
+    parse: Parser,
+}
+
+/// The words which start a command.
+pub(crate) fn keywords() -> impl Iterator<Item = &'static str> {
+    PARSERS
+        .iter()
+        .flat_map(|parser| parser.keywords.iter().copied())
+}
+
+/// Picks the command among those whose parser accepted the input, returning
+/// an error if there is more than one.
+fn choose<'a>(
+    tokenizer: &Tokenizer<'a>,
+    mut success: Vec<(Tokenizer<'a>, Command<'a>)>,
+) -> Option<(Tokenizer<'a>, Command<'a>)> {
+    if success.len() > 1 {
+        let commands = success.iter().map(|(_, c)| c.name()).collect();
+        log::warn!("ambiguous command: {:?}", success);
+        // Don't move the input forwards, like other errors.
+        let mut tok = tokenizer.clone();
+        let err = tok.error(AmbiguousCommand { commands });
+        return Some((tok, Command::Ambiguous(err)));
+    }
+    success.pop()
+}
+
 impl<'a> Input<'a> {
     pub fn new(input: &'a str, bot: Vec<&'a str>) -> Input<'a> {
         let bots: Vec<_> = bot.iter().map(|bot| format!(r"(?:@{bot}\b)")).collect();
@@ -82,66 +197,20 @@ impl<'a> Input<'a> {
 
         let original_tokenizer = tok.clone();
 
-        success.extend(parse_single_command(
-            relabel::RelabelCommand::parse,
-            Command::Relabel,
-            &original_tokenizer,
-        ));
-        success.extend(parse_single_command(
-            assign::AssignCommand::parse,
-            Command::Assign,
-            &original_tokenizer,
-        ));
-        success.extend(parse_single_command(
-            note::NoteCommand::parse,
-            Command::Note,
-            &original_tokenizer,
-        ));
-        success.extend(parse_single_command(
-            ping::PingCommand::parse,
-            Command::Ping,
-            &original_tokenizer,
-        ));
-        success.extend(parse_single_command(
-            nominate::NominateCommand::parse,
-            Command::Nominate,
-            &original_tokenizer,
-        ));
-        success.extend(parse_single_command(
-            prioritize::PrioritizeCommand::parse,
-            Command::Prioritize,
-            &original_tokenizer,
-        ));
-        success.extend(parse_single_command(
-            second::SecondCommand::parse,
-            Command::Second,
-            &original_tokenizer,
-        ));
-        success.extend(parse_single_command(
-            glacier::GlacierCommand::parse,
-            Command::Glacier,
-            &original_tokenizer,
-        ));
-        success.extend(parse_single_command(
-            shortcut::ShortcutCommand::parse,
-            Command::Shortcut,
-            &original_tokenizer,
-        ));
-        success.extend(parse_single_command(
-            close::CloseCommand::parse,
-            Command::Close,
-            &original_tokenizer,
-        ));
-
-        if success.len() > 1 {
-            panic!(
-                "succeeded parsing {:?} to multiple commands: {:?}",
-                &self.all[self.parsed..],
-                success
-            );
+        for parser in PARSERS {
+            success.extend((parser.parse)(&original_tokenizer));
         }
 
-        let (mut tok, c) = success.pop()?;
+        if success.is_empty() {
+            // The bot may just be mentioned, but it may also be a typo.
+            success.extend(parse_single_command(
+                unknown::UnknownCommand::parse,
+                Command::Unknown,
+                &original_tokenizer,
+            ));
+        }
+
+        let (mut tok, c) = choose(&original_tokenizer, success)?;
         // if we errored out while parsing the command do not move the input forwards
         if c.is_ok() {
             self.parsed += tok.position();
@@ -195,21 +264,6 @@ impl<'a> Iterator for Input<'a> {
 }
 
 impl<'a> Command<'a> {
-    pub fn is_ok(&self) -> bool {
-        match self {
-            Command::Relabel(r) => r.is_ok(),
-            Command::Assign(r) => r.is_ok(),
-            Command::Ping(r) => r.is_ok(),
-            Command::Nominate(r) => r.is_ok(),
-            Command::Prioritize(r) => r.is_ok(),
-            Command::Second(r) => r.is_ok(),
-            Command::Glacier(r) => r.is_ok(),
-            Command::Shortcut(r) => r.is_ok(),
-            Command::Close(r) => r.is_ok(),
-            Command::Note(r) => r.is_ok(),
-        }
-    }
-
     pub fn is_err(&self) -> bool {
         !self.is_ok()
     }
@@ -347,3 +401,42 @@ fn review_ignored() {
         assert_eq!(input.next(), None);
     }
 }
+
+#[test]
+fn unknown_commands() {
+    let mut input = Input::new("@bot lable +T-lang", vec!["bot"]);
+    assert_eq!(
+        input.next(),
+        Some(Command::Unknown(Ok(unknown::UnknownCommand {
+            word: "lable".to_string(),
+            suggestion: "label",
+        })))
+    );
+    assert_eq!(input.next(), None);
+
+    // Mentions which don't look like commands are ignored.
+    let mut input = Input::new("thanks @bot for the help", vec!["bot"]);
+    assert_eq!(input.next(), None);
+}
+
+#[test]
+fn ambiguous_commands() {
+    let tok = Tokenizer::new("close");
+    let success = vec![
+        (tok.clone(), Command::Close(Ok(close::CloseCommand))),
+        (tok.clone(), Command::Help(Ok(help::HelpCommand))),
+    ];
+    let (_, command) = choose(&tok, success).unwrap();
+    let err = match command {
+        Command::Ambiguous(err) => err,
+        command => panic!("expected an ambiguity error, got {command:?}"),
+    };
+    assert_eq!(err.position(), 0);
+    assert_eq!(
+        err.source.downcast_ref::<AmbiguousCommand>(),
+        Some(&AmbiguousCommand {
+            commands: vec!["close", "help"]
+        })
+    );
+    assert!(!Command::Ambiguous(err).is_ok());
+}
@@ -39,6 +39,9 @@ impl fmt::Display for ParseError {
     }
 }
 
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["claim", "assign", "release-assignment"];
+
 impl AssignCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
         let mut toks = input.clone();
@@ -4,6 +4,9 @@ use crate::token::{Token, Tokenizer};
 #[derive(PartialEq, Eq, Debug)]
 pub struct CloseCommand;
 
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["close"];
+
 impl CloseCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
         if let Some(Token::Word("close")) = input.peek_token()? {
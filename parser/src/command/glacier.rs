@@ -36,6 +36,9 @@ impl fmt::Display for ParseError {
     }
 }
 
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["glacier"];
+
 impl GlacierCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<GlacierCommand>, Error<'a>> {
         let mut toks = input.clone();
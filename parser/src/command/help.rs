@@ -0,0 +1,42 @@
+//! The help command parser.
+//!
+//! The grammar is as follows:
+//!
+//! ```text
+//! Command: `@bot help`.
+//! ```
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct HelpCommand;
+
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["help"];
+
+impl HelpCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        if let Some(Token::Word("help")) = toks.peek_token()? {
+            toks.next_token()?;
+            *input = toks;
+            Ok(Some(Self))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse(input: &str) -> Result<Option<HelpCommand>, Error<'_>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(HelpCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn test_help() {
+    assert_eq!(parse("help"), Ok(Some(HelpCommand)));
+    assert_eq!(parse("help."), Ok(Some(HelpCommand)));
+    assert_eq!(parse("helpful"), Ok(None));
+}
@@ -49,6 +49,9 @@ impl fmt::Display for ParseError {
     }
 }
 
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["nominate", "beta-nominate", "beta-accept", "beta-approve"];
+
 impl NominateCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
         let mut toks = input.clone();
@@ -21,6 +21,9 @@ impl fmt::Display for ParseError {
     }
 }
 
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["note"];
+
 impl NoteCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
         let mut toks = input.clone();
@@ -34,6 +34,9 @@ impl fmt::Display for ParseError {
     }
 }
 
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["ping"];
+
 impl PingCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
         let mut toks = input.clone();
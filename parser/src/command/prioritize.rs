@@ -4,6 +4,9 @@ pub struct PrioritizeCommand;
 use crate::error::Error;
 use crate::token::{Token, Tokenizer};
 
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["prioritize"];
+
 impl PrioritizeCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
         if let Some(Token::Word("prioritize")) = input.peek_token()? {
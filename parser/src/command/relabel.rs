@@ -126,6 +126,9 @@ fn delta_empty() {
     assert_eq!(err.position(), 1);
 }
 
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["label", "labels", "modify"];
+
 impl RelabelCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
         let mut toks = input.clone();
@@ -4,6 +4,9 @@ use crate::token::{Token, Tokenizer};
 #[derive(PartialEq, Eq, Debug)]
 pub struct SecondCommand;
 
+/// The words which start the command.
+pub const KEYWORDS: &[&str] = &["second", "seconded"];
+
 impl SecondCommand {
     pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
         if let Some(Token::Word("second")) = input.peek_token()? {
@@ -0,0 +1,156 @@
+//! Recognizes misspelled commands.
+//!
+//! This is only tried if no other command matched. If the word following the
+//! bot's name is close to a command keyword, it is returned along with that
+//! keyword, so the user can be told what they probably meant. Other words are
+//! ignored, since the bot may just be mentioned in passing.
+
+use crate::error::Error;
+use crate::token::{Token, Tokenizer};
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct UnknownCommand {
+    /// The word which was not recognized.
+    pub word: String,
+    /// The closest command keyword.
+    pub suggestion: &'static str,
+}
+
+impl UnknownCommand {
+    pub fn parse<'a>(input: &mut Tokenizer<'a>) -> Result<Option<Self>, Error<'a>> {
+        let mut toks = input.clone();
+        let Ok(Some(Token::Word(word))) = toks.next_token() else {
+            return Ok(None);
+        };
+        let Some(suggestion) = suggest(word) else {
+            return Ok(None);
+        };
+        *input = toks;
+        Ok(Some(UnknownCommand {
+            word: word.to_string(),
+            suggestion,
+        }))
+    }
+}
+
+/// Returns the keyword closest to `word`, if it is close enough to be a
+/// likely typo.
+fn suggest(word: &str) -> Option<&'static str> {
+    let word = word.to_lowercase();
+    // Inflected keywords and keywords missing their ending are usually
+    // prose, as in "@rustbot closed this" or "read this, @rustbot".
+    if super::keywords().any(|keyword| is_inflection(&word, keyword)) {
+        return None;
+    }
+    super::keywords()
+        .map(|keyword| (distance(&word, keyword), keyword))
+        .filter(|(distance, keyword)| is_typo(&word, keyword, *distance))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, keyword)| keyword)
+}
+
+/// Whether `word`, at the given distance from `keyword`, is likely a typo of
+/// it.
+///
+/// Two typos are allowed in long keywords. In keywords of five letters or
+/// fewer, changing a letter often makes another word, as in "nope" or "vote"
+/// for "note", so only one swapped, missing or extra letter is.
+fn is_typo(word: &str, keyword: &str, distance: usize) -> bool {
+    if keyword.chars().count() > 5 {
+        return distance <= 2;
+    }
+    if distance > 1 {
+        return false;
+    }
+    // Zero is a difference in case only.
+    if distance == 0 || word.chars().count() != keyword.chars().count() {
+        return true;
+    }
+    // Same length at distance one: either a swap or a changed letter.
+    let mut a: Vec<char> = word.chars().collect();
+    let mut b: Vec<char> = keyword.chars().collect();
+    a.sort_unstable();
+    b.sort_unstable();
+    a == b
+}
+
+/// Whether `word` is `keyword` with a different ending, e.g. "helps" for
+/// "help" or "read" for "ready".
+fn is_inflection(word: &str, keyword: &str) -> bool {
+    word != keyword && (word.starts_with(keyword) || keyword.starts_with(word))
+}
+
+/// The edit distance between two strings, counting insertions, deletions,
+/// substitutions and transpositions of adjacent characters.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // d[i][j] is the distance between the first i characters of `a` and the
+    // first j characters of `b`.
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+fn parse(input: &str) -> Result<Option<UnknownCommand>, Error<'_>> {
+    let mut toks = Tokenizer::new(input);
+    Ok(UnknownCommand::parse(&mut toks)?)
+}
+
+#[test]
+fn typos() {
+    let suggestion = |input| parse(input).unwrap().map(|c| c.suggestion);
+    assert_eq!(suggestion("lable +T-lang"), Some("label"));
+    assert_eq!(suggestion("asign @me"), Some("assign"));
+    assert_eq!(suggestion("Claim"), Some("claim"));
+    assert_eq!(suggestion("pnig compiler"), Some("ping"));
+    assert_eq!(suggestion("labl +T-lang"), Some("label"));
+    assert_eq!(suggestion("reveiwer @me"), Some("reviewer"));
+}
+
+#[test]
+fn unrelated_words() {
+    assert_eq!(parse("is great"), Ok(None));
+    assert_eq!(parse("for the help"), Ok(None));
+    assert_eq!(parse("+T-lang"), Ok(None));
+    assert_eq!(parse(""), Ok(None));
+    assert_eq!(parse("hello there"), Ok(None));
+    assert_eq!(parse("nope"), Ok(None));
+    assert_eq!(parse("vote for this"), Ok(None));
+}
+
+#[test]
+fn prose() {
+    assert_eq!(parse("closed this"), Ok(None));
+    assert_eq!(parse("helps us"), Ok(None));
+    assert_eq!(parse("read this"), Ok(None));
+    assert_eq!(parse("seconds later"), Ok(None));
+    assert_eq!(parse("nominated it"), Ok(None));
+    assert_eq!(parse("labeled it"), Ok(None));
+}
+
+#[test]
+fn edit_distance() {
+    assert_eq!(distance("label", "label"), 0);
+    assert_eq!(distance("lable", "label"), 1);
+    assert_eq!(distance("asign", "assign"), 1);
+    assert_eq!(distance("kitten", "sitting"), 3);
+    assert_eq!(distance("", "help"), 4);
+}
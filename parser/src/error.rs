@@ -29,11 +29,19 @@ impl<'a> Error<'a> {
 impl<'a> fmt::Display for Error<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let space = 10;
-        let end = std::cmp::min(self.input.len(), self.position + space);
+        // Widen the context to whole characters, so slicing doesn't panic.
+        let mut start = self.position.saturating_sub(space);
+        while !self.input.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = std::cmp::min(self.input.len(), self.position + space);
+        while !self.input.is_char_boundary(end) {
+            end += 1;
+        }
         write!(
             f,
             "...'{}' | error: {} at >| '{}'...",
-            &self.input[self.position.saturating_sub(space)..self.position],
+            &self.input[start..self.position],
             self.source,
             &self.input[self.position..end],
         )
@@ -2,6 +2,8 @@ pub mod command;
 pub mod error;
 mod ignore_block;
 mod mentions;
+#[cfg(test)]
+mod proptests;
 mod token;
 
 pub use mentions::get_mentions;
@@ -0,0 +1,111 @@
+//! Property tests checking that arbitrary comments can be parsed without
+//! panicking, and that parsing them is deterministic.
+
+use crate::command::{keywords, Command, Input};
+use crate::error::Error;
+use crate::token::Tokenizer;
+use proptest::prelude::*;
+
+/// Text which looks like comments containing commands, since arbitrary
+/// strings rarely mention the bot.
+fn comment() -> impl Strategy<Value = String> {
+    let part = prop_oneof![
+        Just("@bot".to_string()),
+        Just("r?".to_string()),
+        // Keywords, other words of commands, and misspelled keywords.
+        prop::sample::select(
+            keywords()
+                .chain(["to", "remove", "lable", "asign"])
+                .collect::<Vec<_>>()
+        )
+        .prop_map(str::to_string),
+        prop::sample::select(vec![
+            ".",
+            ",",
+            ";",
+            ":",
+            "!",
+            "?",
+            "(",
+            ")",
+            "\"",
+            "`",
+            "```",
+            "\n",
+            "\r\n",
+            "and",
+            "+T-lang",
+            "-A-diagnostics",
+            "@user",
+            "é",
+            "👍",
+        ])
+        .prop_map(str::to_string),
+        "\\PC{0,8}",
+    ];
+    prop::collection::vec(part, 0..24).prop_map(|parts| parts.join(" "))
+}
+
+fn any_text() -> impl Strategy<Value = String> {
+    prop_oneof![any::<String>(), comment()]
+}
+
+fn parse(text: &str) -> Vec<Command<'_>> {
+    Input::new(text, vec!["bot"]).collect()
+}
+
+fn error<'a, 'b>(command: &'b Command<'a>) -> Option<&'b Error<'a>> {
+    match command {
+        Command::Relabel(Err(e))
+        | Command::Assign(Err(e))
+        | Command::Ping(Err(e))
+        | Command::Nominate(Err(e))
+        | Command::Prioritize(Err(e))
+        | Command::Second(Err(e))
+        | Command::Glacier(Err(e))
+        | Command::Shortcut(Err(e))
+        | Command::Close(Err(e))
+        | Command::Note(Err(e))
+        | Command::Help(Err(e))
+        | Command::Unknown(Err(e))
+        | Command::Ambiguous(e) => Some(e),
+        _ => None,
+    }
+}
+
+proptest! {
+    #[test]
+    fn tokenizer_terminates(text in any_text()) {
+        let mut tok = Tokenizer::new(&text);
+        // Each token consumes at least one character, except the final end
+        // of line.
+        for _ in 0..=text.len() + 1 {
+            let position = tok.position();
+            prop_assert!(text.is_char_boundary(position));
+            match tok.next_token() {
+                Ok(Some(_)) => {}
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    prop_assert!(text.is_char_boundary(e.position()));
+                    let _ = e.to_string();
+                    return Ok(());
+                }
+            }
+        }
+        prop_assert!(false, "tokenizer did not stop on {:?}", text);
+    }
+
+    #[test]
+    fn input_does_not_panic(text in any_text()) {
+        for command in parse(&text) {
+            if let Some(e) = error(&command) {
+                let _ = e.to_string();
+            }
+        }
+    }
+
+    #[test]
+    fn parsing_is_deterministic(text in any_text()) {
+        prop_assert_eq!(parse(&text), parse(&text));
+    }
+}
@@ -0,0 +1,41 @@
+//! Processes a stored webhook delivery again.
+//!
+//! This uses the same environment variables as the server (`DATABASE_URL`,
+//! `GITHUB_API_TOKEN`, ...). With `--dry-run`, requests which would modify
+//! something on GitHub are logged instead of sent.
+
+use reqwest::Client;
+use triagebot::{db, github, handlers::Context};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    dotenv::dotenv().ok();
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (dry_run, delivery_id) = match &args[..] {
+        [flag, id] if flag == "--dry-run" => (true, id),
+        [id] if !id.starts_with('-') => (false, id),
+        _ => {
+            eprintln!("Usage: replay-webhook [--dry-run] <delivery-id>");
+            std::process::exit(2);
+        }
+    };
+
+    let mut gh = github::GithubClient::new_from_env(Client::new())?;
+    if dry_run {
+        gh = gh.dry_run();
+    }
+    let oc = gh.octocrab()?;
+    let ctx = Context {
+        username: std::env::var("TRIAGEBOT_USERNAME").unwrap_or_else(|_| "rustbot".to_owned()),
+        db: db::ClientPool::new(),
+        github: gh,
+        octocrab: oc,
+    };
+
+    let report = db::replay_webhook(&ctx, &*ctx.db.get().await, delivery_id).await?;
+    println!("{}", report);
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use crate::changelogs::ChangelogFormat;
-use crate::github::{GithubClient, Repository};
+use crate::github::{Event, GithubClient, PushEvent, Repository};
+use rust_team_data::v1::Teams;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::{Arc, RwLock};
@@ -7,7 +8,12 @@ use std::time::{Duration, Instant};
 use tracing as log;
 
 static CONFIG_FILE_NAME: &str = "triagebot.toml";
-const REFRESH_EVERY: Duration = Duration::from_secs(2 * 60); // Every two minutes
+/// The repository in each organization which holds the organization-wide
+/// default configuration.
+static ORG_CONFIG_REPO: &str = ".github";
+// Pushes changing the configuration evict it from the cache, so this is only
+// a fallback in case such a push is missed.
+const REFRESH_EVERY: Duration = Duration::from_secs(60 * 60); // Every hour
 
 lazy_static::lazy_static! {
     static ref CONFIG_CACHE:
@@ -15,40 +21,187 @@ lazy_static::lazy_static! {
         RwLock::new(HashMap::new());
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "kebab-case")]
+/// The configuration of a repository, read from `triagebot.toml`.
+///
+/// Each table enables a feature. Features without a table are disabled.
 pub(crate) struct Config {
+    /// Allows changing labels with `@rustbot label`.
     pub(crate) relabel: Option<RelabelConfig>,
+    /// Allows claiming issues, and assigns reviewers to new pull requests.
     pub(crate) assign: Option<AssignConfig>,
+    /// Allows pinging teams with `@rustbot ping`.
     pub(crate) ping: Option<PingConfig>,
+    /// Allows nominating issues for team meetings with `@rustbot nominate`.
     pub(crate) nominate: Option<NominateConfig>,
+    /// Allows requesting prioritization with `@rustbot prioritize`.
     pub(crate) prioritize: Option<PrioritizeConfig>,
+    /// Enables the major change proposal process.
     pub(crate) major_change: Option<MajorChangeConfig>,
+    /// Allows adding issues to glacier with `@rustbot glacier`.
     pub(crate) glacier: Option<GlacierConfig>,
+    /// Allows closing issues with `@rustbot close`.
     pub(crate) close: Option<CloseConfig>,
+    /// Adds labels automatically, based on other labels or changed files.
     pub(crate) autolabel: Option<AutolabelConfig>,
+    /// Posts to Zulip when labels change.
     pub(crate) notify_zulip: Option<NotifyZulipConfig>,
+    /// Creates GitHub releases from the changelog.
     pub(crate) github_releases: Option<GitHubReleasesConfig>,
+    /// Updates labels when a review is submitted.
     pub(crate) review_submitted: Option<ReviewSubmittedConfig>,
+    /// Updates labels when a review is requested.
     pub(crate) review_requested: Option<ReviewRequestedConfig>,
+    /// Allows `@rustbot ready`, `author` and `blocked` to update labels.
     pub(crate) shortcut: Option<ShortcutConfig>,
+    /// Allows adding notes to the issue summary with `@rustbot note`.
     pub(crate) note: Option<NoteConfig>,
+    /// Mentions people when pull requests change certain paths.
     pub(crate) mentions: Option<MentionsConfig>,
+    /// Flags pull requests containing merge commits.
     pub(crate) no_merges: Option<NoMergesConfig>,
+    /// If `true`, pull requests targeting another branch than the default
+    /// use the `triagebot.toml` of that branch, if it has one.
+    ///
+    /// Only the value on the default branch has an effect.
+    #[serde(default)]
+    pub(crate) base_branch_config: bool,
+    /// If `true`, changes which triagebot would make on GitHub and Zulip for
+    /// this repository are only recorded, see `/admin/dry-run`.
+    #[serde(default)]
+    pub(crate) dry_run: bool,
+    /// If `true`, mentions of triagebot followed by a misspelled command get
+    /// a reply suggesting the command that was probably meant.
+    #[serde(default = "Config::command_suggestions_default")]
+    pub(crate) command_suggestions: bool,
+}
+
+impl Config {
+    fn command_suggestions_default() -> bool {
+        true
+    }
+
+    /// Checks for mistakes which deserialize fine, but which the handlers
+    /// would reject or silently ignore, such as invalid patterns or references
+    /// to teams which don't exist.
+    ///
+    /// Returns a description of each problem found, in a stable order.
+    pub(crate) fn validate(&self, teams: &Teams) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(assign) = &self.assign {
+            assign.validate(teams, &mut problems);
+        }
+        if let Some(relabel) = &self.relabel {
+            for pattern in &relabel.allow_unauthenticated {
+                let glob = pattern.strip_prefix('!').unwrap_or(pattern);
+                if let Err(e) = glob::Pattern::new(glob) {
+                    problems.push(format!(
+                        "`relabel.allow-unauthenticated` pattern `{pattern}` is not valid: {e}"
+                    ));
+                }
+            }
+        }
+        if let Some(autolabel) = &self.autolabel {
+            for (label, cfg) in sorted(&autolabel.labels) {
+                for pattern in &cfg.exclude_labels {
+                    if let Err(e) = glob::Pattern::new(pattern) {
+                        problems.push(format!(
+                            "`autolabel.\"{label}\".exclude_labels` pattern `{pattern}` \
+                             is not valid: {e}"
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(ping) = &self.ping {
+            for (team, _) in sorted(&ping.teams) {
+                if !teams.teams.contains_key(team.as_str()) {
+                    problems.push(format!(
+                        "`ping.{team}` refers to a team which does not exist"
+                    ));
+                }
+            }
+        }
+        problems
+    }
+}
+
+/// The table of a handler in `triagebot.toml`.
+pub(crate) trait HandlerConfig: Sync {
+    /// The key of the table.
+    const KEY: &'static str;
+
+    /// Returns the table, if the repository has one.
+    fn get(config: &Config) -> Option<&Self>;
+}
+
+/// Implements [`HandlerConfig`] for the type of each listed field of
+/// [`Config`].
+macro_rules! handler_configs {
+    ($($field:ident: $ty:ty = $key:literal,)*) => {
+        $(impl HandlerConfig for $ty {
+            const KEY: &'static str = $key;
+
+            fn get(config: &Config) -> Option<&$ty> {
+                config.$field.as_ref()
+            }
+        })*
+
+        /// The field and key of each handler's table, to check that they
+        /// match.
+        #[cfg(test)]
+        const HANDLER_CONFIG_KEYS: &[(&str, &str)] = &[$((stringify!($field), $key),)*];
+    };
+}
+
+handler_configs! {
+    relabel: RelabelConfig = "relabel",
+    assign: AssignConfig = "assign",
+    ping: PingConfig = "ping",
+    nominate: NominateConfig = "nominate",
+    prioritize: PrioritizeConfig = "prioritize",
+    major_change: MajorChangeConfig = "major-change",
+    glacier: GlacierConfig = "glacier",
+    close: CloseConfig = "close",
+    autolabel: AutolabelConfig = "autolabel",
+    notify_zulip: NotifyZulipConfig = "notify-zulip",
+    review_requested: ReviewRequestedConfig = "review-requested",
+    shortcut: ShortcutConfig = "shortcut",
+    note: NoteConfig = "note",
+    mentions: MentionsConfig = "mentions",
+    no_merges: NoMergesConfig = "no-merges",
+}
+
+/// The whole configuration, for handlers which are always enabled.
+impl HandlerConfig for Config {
+    const KEY: &'static str = "";
+
+    fn get(config: &Config) -> Option<&Config> {
+        Some(config)
+    }
+}
+
+/// Returns the entries of a map sorted by key, so problems are reported in a
+/// stable order.
+fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    entries
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NominateConfig {
-    // team name -> label
+    /// Team name -> label
     pub(crate) teams: HashMap<String, String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct PingConfig {
-    // team name -> message
-    // message will have the cc string appended
+    /// Team name -> message.
+    /// The message will have the cc string appended.
     #[serde(flatten)]
-    teams: HashMap<String, PingTeamConfig>,
+    pub(crate) teams: HashMap<String, PingTeamConfig>,
 }
 
 impl PingConfig {
@@ -67,7 +220,7 @@ impl PingConfig {
     }
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct PingTeamConfig {
     pub(crate) message: String,
     #[serde(default)]
@@ -75,7 +228,7 @@ pub(crate) struct PingTeamConfig {
     pub(crate) label: Option<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct AssignConfig {
     /// If `true`, then posts a warning comment if the PR is opened against a
     /// different branch than the default (usually master or main).
@@ -102,9 +255,111 @@ impl AssignConfig {
             .iter()
             .any(|vacationer| name_lower == vacationer.to_lowercase())
     }
+
+    fn validate(&self, teams: &Teams, problems: &mut Vec<String>) {
+        for (pattern, names) in sorted(&self.owners) {
+            let result = ignore::gitignore::GitignoreBuilder::new("/")
+                .add_line(None, pattern)
+                .and_then(|builder| builder.build());
+            if let Err(e) = result {
+                problems.push(format!(
+                    "`assign.owners` pattern `{pattern}` is not valid: {e}"
+                ));
+            }
+            for name in names {
+                if !self.is_known_name(teams, name) {
+                    problems.push(format!(
+                        "`assign.owners` entry `{pattern}` refers to `{name}`, \
+                         which is neither an ad-hoc group nor a team"
+                    ));
+                }
+            }
+        }
+        for (group, members) in sorted(&self.adhoc_groups) {
+            for name in members {
+                if !self.is_known_name(teams, name) {
+                    problems.push(format!(
+                        "`assign.adhoc_groups.{group}` refers to `{name}`, \
+                         which is neither an ad-hoc group nor a team"
+                    ));
+                }
+            }
+        }
+        if let Some(cycle) = self.find_group_cycle() {
+            problems.push(format!(
+                "`assign.adhoc_groups` contains a cycle: {}",
+                cycle.join(" -> ")
+            ));
+        }
+    }
+
+    /// Returns the ad-hoc group a name in `owners` or `adhoc_groups` refers
+    /// to, if any.
+    ///
+    /// Like the handler, this allows an organization prefix such as
+    /// `rust-lang/compiler`, but accepts any organization since the
+    /// repository is not known here.
+    fn adhoc_group(&self, name: &str) -> Option<&str> {
+        let name = name.strip_prefix('@').unwrap_or(name);
+        let name = name.split_once('/').map_or(name, |(_, group)| group);
+        self.adhoc_groups
+            .get_key_value(name)
+            .map(|(group, _)| group.as_str())
+    }
+
+    /// Whether a name with a `/` refers to an ad-hoc group or a team. Names
+    /// without one are assumed to be users, which are not checked.
+    fn is_known_name(&self, teams: &Teams, name: &str) -> bool {
+        let name = name.strip_prefix('@').unwrap_or(name);
+        if !name.contains('/') || self.adhoc_group(name).is_some() {
+            return true;
+        }
+        let team = name.strip_prefix("rust-lang/").unwrap_or(name);
+        teams.teams.contains_key(team)
+    }
+
+    /// Finds an ad-hoc group which (indirectly) includes itself, returning
+    /// the groups along the cycle.
+    fn find_group_cycle(&self) -> Option<Vec<&str>> {
+        fn visit<'a>(
+            config: &'a AssignConfig,
+            group: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> bool {
+            if let Some(start) = path.iter().position(|g| *g == group) {
+                path.drain(..start);
+                path.push(group);
+                return true;
+            }
+            if done.contains(group) {
+                return false;
+            }
+            path.push(group);
+            for member in &config.adhoc_groups[group] {
+                if let Some(next) = config.adhoc_group(member) {
+                    if visit(config, next, path, done) {
+                        return true;
+                    }
+                }
+            }
+            path.pop();
+            done.insert(group);
+            false
+        }
+
+        let mut done = HashSet::new();
+        for (group, _) in sorted(&self.adhoc_groups) {
+            let mut path = Vec::new();
+            if visit(self, group, &mut path, &mut done) {
+                return Some(path);
+            }
+        }
+        None
+    }
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NoMergesConfig {
     /// No action will be taken on PRs with these substrings in the title.
     #[serde(default)]
@@ -120,44 +375,46 @@ pub(crate) struct NoMergesConfig {
     pub(crate) message: Option<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NoteConfig {
     #[serde(default)]
+    #[schemars(skip)]
     _empty: (),
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct MentionsConfig {
     #[serde(flatten)]
     pub(crate) paths: HashMap<String, MentionsPathConfig>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct MentionsPathConfig {
     pub(crate) message: Option<String>,
     #[serde(default)]
     pub(crate) cc: Vec<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct RelabelConfig {
     #[serde(default)]
     pub(crate) allow_unauthenticated: Vec<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct ShortcutConfig {
     #[serde(default)]
+    #[schemars(skip)]
     _empty: (),
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct PrioritizeConfig {
     pub(crate) label: String,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct AutolabelConfig {
     #[serde(flatten)]
     pub(crate) labels: HashMap<String, AutolabelLabelConfig>,
@@ -175,7 +432,7 @@ impl AutolabelConfig {
     }
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct AutolabelLabelConfig {
     #[serde(default)]
     pub(crate) trigger_labels: Vec<String>,
@@ -189,13 +446,13 @@ pub(crate) struct AutolabelLabelConfig {
     pub(crate) new_issue: bool,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NotifyZulipConfig {
     #[serde(flatten)]
     pub(crate) labels: HashMap<String, NotifyZulipLabelConfig>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct NotifyZulipLabelConfig {
     pub(crate) zulip_stream: u64,
     pub(crate) topic: String,
@@ -207,7 +464,7 @@ pub(crate) struct NotifyZulipLabelConfig {
     pub(crate) required_labels: Vec<String>,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct MajorChangeConfig {
     /// A username (typically a group, e.g. T-lang) to ping on Zulip for newly
     /// opened proposals.
@@ -242,24 +499,25 @@ impl MajorChangeConfig {
     }
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct GlacierConfig {}
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct CloseConfig {}
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct ReviewSubmittedConfig {
     pub(crate) review_labels: Vec<String>,
     pub(crate) reviewed_label: String,
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 pub(crate) struct ReviewRequestedConfig {
     pub(crate) remove_labels: Vec<String>,
     pub(crate) add_labels: Vec<String>,
 }
 
+/// Returns the configuration on the default branch of a repository.
 pub(crate) async fn get(
     gh: &GithubClient,
     repo: &Repository,
@@ -269,16 +527,71 @@ pub(crate) async fn get(
         config
     } else {
         log::trace!("fetching fresh config for {}", repo.full_name);
-        let res = get_fresh_config(gh, repo).await;
-        CONFIG_CACHE
-            .write()
-            .unwrap()
-            .insert(repo.full_name.to_string(), (res.clone(), Instant::now()));
+        let res = get_fresh_config(gh, repo, &repo.default_branch).await;
+        cache_config(repo.full_name.to_string(), &res);
         res
     }
 }
 
-#[derive(PartialEq, Eq, Debug, serde::Deserialize)]
+/// Returns the configuration which applies to an event.
+///
+/// This is the configuration on the default branch, unless it enables
+/// `base-branch-config` and the event is about a pull request targeting
+/// another branch which has a `triagebot.toml`.
+pub(crate) async fn get_for_event(
+    gh: &GithubClient,
+    event: &Event,
+) -> Result<Arc<Config>, ConfigurationError> {
+    let repo = event.repo();
+    let config = get(gh, repo).await?;
+    if !config.base_branch_config {
+        return Ok(config);
+    }
+    let Some(issue) = event.issue() else {
+        return Ok(config);
+    };
+    let branch = match issue.base_ref(gh).await {
+        Ok(Some(branch)) if branch != repo.default_branch => branch,
+        Ok(_) => return Ok(config),
+        Err(e) => return Err(ConfigurationError::Http(Arc::new(e))),
+    };
+
+    let key = branch_cache_key(&repo.full_name, &branch);
+    if let Some(config) = get_cached_config(&key) {
+        log::trace!("returning config for {key} from cache");
+        return config;
+    }
+    log::trace!("fetching fresh config for {key}");
+    let res = match get_fresh_config(gh, repo, &branch).await {
+        // Branches without their own file, such as those created before it
+        // was added, use the default branch's.
+        Err(ConfigurationError::Missing) => Ok(config),
+        res => res,
+    };
+    cache_config(key, &res);
+    res
+}
+
+/// Caches a configuration, or the problem with it.
+///
+/// Failures to fetch it are not cached, as the event is retried and should
+/// fetch it again.
+fn cache_config(key: String, res: &Result<Arc<Config>, ConfigurationError>) {
+    if let Err(ConfigurationError::Http(_)) = res {
+        return;
+    }
+    CONFIG_CACHE
+        .write()
+        .unwrap()
+        .insert(key, (res.clone(), Instant::now()));
+}
+
+/// The key of the cached configuration of a branch other than the default.
+fn branch_cache_key(repo: &str, branch: &str) -> String {
+    format!("{repo}@{branch}")
+}
+
+#[derive(PartialEq, Eq, Debug, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct GitHubReleasesConfig {
     pub(crate) format: ChangelogFormat,
@@ -287,6 +600,59 @@ pub(crate) struct GitHubReleasesConfig {
     pub(crate) changelog_branch: String,
 }
 
+/// Evicts cached configuration which a push may have changed.
+///
+/// A push to the default branch of a repository touching its
+/// `triagebot.toml` evicts that repository (including its other branches),
+/// and a push to another branch evicts that branch. A push to the
+/// organization's `.github` repository touching the organization-wide default
+/// evicts every repository in the organization.
+pub(crate) fn evict_on_push(repo: &Repository, event: &PushEvent) {
+    if !event.may_touch(CONFIG_FILE_NAME) {
+        return;
+    }
+    let scope = if event.is_default_branch() {
+        if repo.full_name == org_config_repo(repo) {
+            repo.owner().to_string()
+        } else {
+            repo.full_name.clone()
+        }
+    } else if let Some(branch) = event.git_ref.strip_prefix("refs/heads/") {
+        branch_cache_key(&repo.full_name, branch)
+    } else {
+        return;
+    };
+    let evicted = clear_cache(Some(&scope));
+    log::info!(
+        "{} changed triagebot.toml, evicted {evicted} cached configurations",
+        repo.full_name
+    );
+}
+
+/// Evicts cached configuration, so it is fetched again the next time it is
+/// needed.
+///
+/// `scope` is either a branch (`owner/name@branch`), a repository
+/// (`owner/name`) to evict all of its branches, an organization or user
+/// (`owner`) to evict all of its repositories, or `None` to evict everything.
+/// Returns the number of entries evicted.
+pub fn clear_cache(scope: Option<&str>) -> usize {
+    let mut cache = CONFIG_CACHE.write().unwrap();
+    let before = cache.len();
+    match scope {
+        None => cache.clear(),
+        Some(scope) => {
+            let branches = format!("{scope}@");
+            cache.retain(|key, _| {
+                key != scope
+                    && !key.starts_with(&branches)
+                    && key.split_once('/').map(|(owner, _)| owner) != Some(scope)
+            })
+        }
+    }
+    before - cache.len()
+}
+
 fn get_cached_config(repo: &str) -> Option<Result<Arc<Config>, ConfigurationError>> {
     let cache = CONFIG_CACHE.read().unwrap();
     cache.get(repo).and_then(|(config, fetch_time)| {
@@ -298,24 +664,177 @@ fn get_cached_config(repo: &str) -> Option<Result<Arc<Config>, ConfigurationErro
     })
 }
 
+/// Loads the configuration of a repository from the given branch.
+///
+/// The repository's `triagebot.toml` is merged over the organization-wide
+/// default in the `triagebot.toml` of the organization's `.github`
+/// repository, if there is one. The default only applies to repositories
+/// which have their own `triagebot.toml`.
 async fn get_fresh_config(
     gh: &GithubClient,
     repo: &Repository,
+    branch: &str,
 ) -> Result<Arc<Config>, ConfigurationError> {
     let contents = gh
-        .raw_file(&repo.full_name, &repo.default_branch, CONFIG_FILE_NAME)
+        .raw_file(&repo.full_name, branch, CONFIG_FILE_NAME)
         .await
         .map_err(|e| ConfigurationError::Http(Arc::new(e)))?
         .ok_or(ConfigurationError::Missing)?;
-    let config = Arc::new(toml::from_slice::<Config>(&contents).map_err(ConfigurationError::Toml)?);
-    log::debug!("fresh configuration for {}: {:?}", repo.full_name, config);
+    let (config, unknown_keys) = parse(gh, repo, &contents).await?;
+    for key in unknown_keys {
+        log::warn!(
+            "ignoring unknown key `{key}` in the configuration of {}@{branch}",
+            repo.full_name
+        );
+    }
+    let config = Arc::new(config);
+    log::debug!(
+        "fresh configuration for {}@{branch}: {:?}",
+        repo.full_name,
+        config
+    );
     Ok(config)
 }
 
+/// Parses the contents of a repository's `triagebot.toml`, merged over the
+/// organization-wide default.
+///
+/// Also returns the keys which were ignored because they are unknown, such as
+/// misspelled table names.
+pub(crate) async fn parse(
+    gh: &GithubClient,
+    repo: &Repository,
+    contents: &[u8],
+) -> Result<(Config, Vec<String>), ConfigurationError> {
+    let mut value = toml::from_slice::<toml::Value>(&contents).map_err(ConfigurationError::Toml)?;
+    let options = InheritOptions::take(&mut value).map_err(ConfigurationError::Toml)?;
+
+    let value = if options.inherit && repo.full_name != org_config_repo(repo) {
+        let base = gh
+            .raw_file(&org_config_repo(repo), "HEAD", CONFIG_FILE_NAME)
+            .await
+            .map_err(|e| ConfigurationError::Http(Arc::new(e)))?;
+        match base {
+            Some(base) => {
+                let mut base =
+                    toml::from_slice::<toml::Value>(&base).map_err(ConfigurationError::OrgToml)?;
+                // The organization's own options don't apply to other
+                // repositories.
+                InheritOptions::take(&mut base).map_err(ConfigurationError::OrgToml)?;
+                for path in &options.exclude_inherited {
+                    remove_path(&mut base, path);
+                }
+                merge(&mut base, value);
+                base
+            }
+            None => value,
+        }
+    } else {
+        value
+    };
+
+    deserialize(value).map_err(ConfigurationError::Toml)
+}
+
+/// Deserializes a parsed `triagebot.toml`, also returning the keys which were
+/// ignored because they are unknown.
+fn deserialize(value: toml::Value) -> Result<(Config, Vec<String>), toml::de::Error> {
+    let mut unknown_keys = Vec::new();
+    let config = serde_ignored::deserialize(value, |path| unknown_keys.push(path.to_string()))?;
+    Ok((config, unknown_keys))
+}
+
+/// Returns a JSON Schema describing `triagebot.toml`.
+pub fn schema() -> schemars::schema::RootSchema {
+    let mut schema = schemars::schema_for!(Config);
+    // The inheritance options are taken out before deserializing `Config`.
+    let options = schemars::schema_for!(InheritOptions);
+    if let (Some(object), Some(options)) = (schema.schema.object.as_mut(), options.schema.object) {
+        object.properties.extend(options.properties);
+    }
+    schema
+}
+
+fn org_config_repo(repo: &Repository) -> String {
+    format!("{}/{ORG_CONFIG_REPO}", repo.owner())
+}
+
+/// Top-level keys of a repository's `triagebot.toml` which control what is
+/// inherited from the organization-wide default.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+struct InheritOptions {
+    /// Set to `false` to ignore the organization-wide default entirely.
+    #[serde(default = "InheritOptions::inherit_default")]
+    inherit: bool,
+    /// Tables or keys of the organization-wide default to leave out, as
+    /// dotted paths such as `ping` or `assign.adhoc_groups`.
+    #[serde(default)]
+    exclude_inherited: Vec<String>,
+}
+
+impl InheritOptions {
+    fn inherit_default() -> bool {
+        true
+    }
+
+    /// Removes the options from a parsed `triagebot.toml`, so they don't end
+    /// up in the merged configuration.
+    fn take(value: &mut toml::Value) -> Result<InheritOptions, toml::de::Error> {
+        let mut options = toml::value::Table::new();
+        if let Some(table) = value.as_table_mut() {
+            for key in ["inherit", "exclude-inherited"] {
+                if let Some(option) = table.remove(key) {
+                    options.insert(key.to_string(), option);
+                }
+            }
+        }
+        toml::Value::Table(options).try_into()
+    }
+}
+
+/// Merges `overlay` into `base`. Tables are merged key by key, anything else
+/// in `overlay` (including arrays) replaces what is in `base`.
+fn merge(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Removes the value at a dotted path such as `assign.adhoc_groups`, if it
+/// exists.
+fn remove_path(value: &mut toml::Value, path: &str) {
+    let mut keys: Vec<&str> = path.split('.').collect();
+    let Some(last) = keys.pop() else {
+        return;
+    };
+    let mut table = value.as_table_mut();
+    for key in keys {
+        table = table
+            .and_then(|t| t.get_mut(key))
+            .and_then(|v| v.as_table_mut());
+    }
+    if let Some(table) = table {
+        table.remove(last);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum ConfigurationError {
     Missing,
     Toml(toml::de::Error),
+    /// The organization-wide default configuration is malformed.
+    OrgToml(toml::de::Error),
     Http(Arc<anyhow::Error>),
 }
 
@@ -332,6 +851,12 @@ impl fmt::Display for ConfigurationError {
             ConfigurationError::Toml(e) => {
                 write!(f, "Malformed `triagebot.toml` in default branch.\n{e}")
             }
+            ConfigurationError::OrgToml(e) => {
+                write!(
+                    f,
+                    "Malformed organization-wide `triagebot.toml` in the `{ORG_CONFIG_REPO}` repository.\n{e}"
+                )
+            }
             ConfigurationError::Http(e) => {
                 write!(
                     f,
@@ -346,6 +871,13 @@ impl fmt::Display for ConfigurationError {
 mod tests {
     use super::*;
 
+    #[test]
+    fn handler_config_keys() {
+        for (field, key) in HANDLER_CONFIG_KEYS {
+            assert_eq!(field.replace('_', "-"), *key);
+        }
+    }
+
     #[test]
     fn sample() {
         let config = r#"
@@ -431,7 +963,227 @@ mod tests {
                 review_requested: None,
                 mentions: None,
                 no_merges: None,
+                base_branch_config: false,
+                dry_run: false,
+                command_suggestions: true,
             }
         );
     }
+
+    #[test]
+    fn inherit() {
+        let mut base: toml::Value = toml::from_str(
+            r#"
+            inherit = false
+
+            [relabel]
+            allow-unauthenticated = ["A-*", "C-*"]
+
+            [assign]
+            warn_non_default_branch = true
+
+            [assign.adhoc_groups]
+            compiler = ["@alice"]
+            libs = ["@bob"]
+
+            [ping.compiler]
+            message = "Hello"
+            "#,
+        )
+        .unwrap();
+        let mut repo: toml::Value = toml::from_str(
+            r#"
+            exclude-inherited = ["ping", "assign.adhoc_groups.libs", "missing.key"]
+
+            [relabel]
+            allow-unauthenticated = ["T-*"]
+
+            [assign.adhoc_groups]
+            compiler = ["@carol"]
+
+            [shortcut]
+            "#,
+        )
+        .unwrap();
+
+        let options = InheritOptions::take(&mut repo).unwrap();
+        assert!(options.inherit);
+        assert_eq!(
+            options.exclude_inherited,
+            ["ping", "assign.adhoc_groups.libs", "missing.key"]
+        );
+        assert!(!InheritOptions::take(&mut base).unwrap().inherit);
+        for path in &options.exclude_inherited {
+            remove_path(&mut base, path);
+        }
+        merge(&mut base, repo);
+
+        let config = base.try_into::<Config>().unwrap();
+        assert_eq!(
+            config.relabel,
+            Some(RelabelConfig {
+                allow_unauthenticated: vec!["T-*".into()],
+            })
+        );
+        let assign = config.assign.unwrap();
+        assert!(assign.warn_non_default_branch);
+        assert_eq!(
+            assign.adhoc_groups,
+            HashMap::from([("compiler".to_string(), vec!["@carol".to_string()])])
+        );
+        assert_eq!(config.ping, None);
+        assert_eq!(config.shortcut, Some(ShortcutConfig { _empty: () }));
+    }
+
+    #[test]
+    fn inherit_options_type_error() {
+        let mut repo: toml::Value = toml::from_str("inherit = \"no\"").unwrap();
+        assert!(InheritOptions::take(&mut repo).is_err());
+    }
+
+    #[test]
+    fn clear_cache_scope() {
+        let insert = |repo: &str| {
+            CONFIG_CACHE.write().unwrap().insert(
+                repo.to_string(),
+                (Err(ConfigurationError::Missing), Instant::now()),
+            );
+        };
+        for repo in ["cache-org/a", "cache-org/b", "cache-org-2/a"] {
+            insert(repo);
+        }
+        assert_eq!(clear_cache(Some("cache-org/a")), 1);
+        assert!(get_cached_config("cache-org/b").is_some());
+        assert_eq!(clear_cache(Some("cache-org")), 1);
+        assert!(get_cached_config("cache-org/b").is_none());
+        assert!(get_cached_config("cache-org-2/a").is_some());
+        assert_eq!(clear_cache(Some("cache-org-2/a")), 1);
+
+        for key in ["cache-org/a", "cache-org/a@beta", "cache-org/a@release/1.0"] {
+            insert(key);
+        }
+        assert_eq!(clear_cache(Some("cache-org/a@beta")), 1);
+        assert_eq!(clear_cache(Some("cache-org/a")), 2);
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let value: toml::Value = toml::from_str(
+            r#"
+            [review_submitted]
+            review_labels = ["S-waiting-on-review"]
+            reviewed_label = "S-waiting-on-author"
+
+            [assign]
+            warn_non_default_branch = true
+            ownres = { "/src" = ["alice"] }
+            "#,
+        )
+        .unwrap();
+        let (config, unknown_keys) = deserialize(value).unwrap();
+        assert!(config.assign.unwrap().warn_non_default_branch);
+        assert_eq!(config.review_submitted, None);
+        let mut unknown_keys = unknown_keys;
+        unknown_keys.sort();
+        assert_eq!(unknown_keys, ["assign.ownres", "review_submitted"]);
+    }
+
+    #[test]
+    fn schema() {
+        let schema = serde_json::to_value(super::schema()).unwrap();
+        let properties = &schema["properties"];
+        assert!(properties["review-submitted"].is_object());
+        assert!(properties["exclude-inherited"].is_object());
+        assert_eq!(
+            properties["no-merges"]["description"],
+            "Flags pull requests containing merge commits."
+        );
+        let assign = &schema["definitions"]["AssignConfig"]["properties"];
+        assert_eq!(
+            assign["adhoc_groups"]["description"],
+            "Ad-hoc groups that can be referred to in `owners`."
+        );
+        assert!(schema["definitions"]["NoteConfig"]["properties"]["_empty"].is_null());
+    }
+
+    fn teams(names: &[&str]) -> Teams {
+        let mut teams = serde_json::json!({});
+        for name in names {
+            teams[name] = serde_json::json!({
+                "name": name,
+                "kind": "team",
+                "members": [],
+                "alumni": [],
+                "discord": [],
+            });
+        }
+        serde_json::from_value(teams).unwrap()
+    }
+
+    #[test]
+    fn validate() {
+        let config = r#"
+            [assign.adhoc_groups]
+            compiler = ["@alice", "reviewers"]
+            libs = ["rust-lang/missing"]
+            reviewers = ["rust-lang/compiler-team", "rust-lang/compiler"]
+
+            [assign.owners]
+            "/library/**" = ["@libs"]
+            "/src" = ["compiler", "bob"]
+            "/x[" = ["bob"]
+
+            [relabel]
+            allow-unauthenticated = ["A-*", "!C-["]
+
+            [autolabel."T-compiler"]
+            exclude_labels = ["T-["]
+
+            [ping.compiler-team]
+            message = "Hello"
+
+            [ping.missing]
+            message = "Hello"
+        "#;
+        let config = toml::from_str::<Config>(config).unwrap();
+        let problems = config.validate(&teams(&["compiler-team"]));
+        assert_eq!(problems.len(), 6, "{problems:#?}");
+        assert!(problems[0].starts_with("`assign.owners` pattern `/x[` is not valid: "));
+        assert_eq!(
+            problems[1],
+            "`assign.adhoc_groups.libs` refers to `rust-lang/missing`, \
+             which is neither an ad-hoc group nor a team"
+        );
+        assert_eq!(
+            problems[2],
+            "`assign.adhoc_groups` contains a cycle: compiler -> reviewers -> compiler"
+        );
+        assert!(problems[3].starts_with("`relabel.allow-unauthenticated` pattern `!C-[`"));
+        assert!(problems[4].starts_with("`autolabel.\"T-compiler\".exclude_labels` pattern `T-[`"));
+        assert_eq!(
+            problems[5],
+            "`ping.missing` refers to a team which does not exist"
+        );
+    }
+
+    #[test]
+    fn validate_valid() {
+        let config = r#"
+            [assign.adhoc_groups]
+            compiler = ["@alice", "rust-lang/compiler-team"]
+            fallback = ["compiler"]
+
+            [assign.owners]
+            "*" = ["fallback"]
+            "/compiler" = ["compiler", "@bob"]
+
+            [relabel]
+            allow-unauthenticated = ["A-*", "!A-bad"]
+        "#;
+        let config = toml::from_str::<Config>(config).unwrap();
+        assert_eq!(
+            config.validate(&teams(&["compiler-team"])),
+            Vec::<String>::new()
+        );
+    }
 }
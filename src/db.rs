@@ -1,5 +1,5 @@
 use crate::handlers::jobs::handle_job;
-use crate::{db::jobs::*, handlers::Context};
+use crate::{db::jobs::*, db::webhooks::WebhookDelivery, handlers::Context};
 use anyhow::Context as _;
 use chrono::Utc;
 use native_tls::{Certificate, TlsConnector};
@@ -12,6 +12,7 @@ pub mod issue_data;
 pub mod jobs;
 pub mod notifications;
 pub mod rustc_commits;
+pub mod webhooks;
 
 const CERT_URL: &str = "https://s3.amazonaws.com/rds-downloads/rds-ca-2019-root.pem";
 
@@ -26,6 +27,7 @@ lazy_static::lazy_static! {
     };
 }
 
+#[derive(Clone)]
 pub struct ClientPool {
     connections: Arc<Mutex<Vec<tokio_postgres::Client>>>,
     permits: Arc<Semaphore>,
@@ -199,19 +201,26 @@ pub async fn schedule_jobs(db: &DbClient, jobs: Vec<JobSchedule>) -> anyhow::Res
 }
 
 pub async fn run_scheduled_jobs(ctx: &Context, db: &DbClient) -> anyhow::Result<()> {
-    let jobs = get_jobs_to_execute(&db).await.unwrap();
+    let jobs = get_jobs_to_execute(&db).await?;
     tracing::trace!("jobs to execute: {:#?}", jobs);
 
     for job in jobs.iter() {
         update_job_executed_at(&db, &job.id).await?;
 
-        match handle_job(&ctx, &job.name, &job.metadata).await {
+        let result = handle_job(&ctx, &job.name, &job.metadata);
+        match crate::handlers::catch_panic(&job.name, result).await {
             Ok(_) => {
                 tracing::trace!("job successfully executed (id={})", job.id);
+                crate::metrics::JOBS
+                    .with_label_values(&[&job.name, "success"])
+                    .inc();
                 delete_job(&db, &job.id).await?;
             }
             Err(e) => {
                 tracing::error!("job failed on execution (id={:?}, error={:?})", job.id, e);
+                crate::metrics::JOBS
+                    .with_label_values(&[&job.name, "failure"])
+                    .inc();
                 update_job_error_message(&db, &job.id, &e.to_string()).await?;
             }
         }
@@ -220,6 +229,153 @@ pub async fn run_scheduled_jobs(ctx: &Context, db: &DbClient) -> anyhow::Result<
     Ok(())
 }
 
+// How often the webhook queue is checked for deliveries which need to be
+// retried. New deliveries are processed immediately when they are received.
+pub const WEBHOOK_RETRY_CADENCE_IN_SECS: u64 = 15;
+
+// How many times processing a webhook delivery is attempted before giving up.
+const WEBHOOK_MAX_ATTEMPTS: i32 = 6;
+
+// How long processed webhook deliveries are kept around for replaying.
+const WEBHOOK_RETENTION_DAYS: i32 = 30;
+
+/// Processes a webhook delivery right after it has been received.
+pub async fn process_new_webhook(ctx: &Context, db: &DbClient, delivery_id: &str) {
+    match webhooks::claim_delivery(db, delivery_id).await {
+        Ok(Some(delivery)) => process_webhook_delivery(ctx, db, delivery).await,
+        Ok(None) => {
+            tracing::debug!("webhook delivery {delivery_id} already claimed");
+        }
+        Err(e) => {
+            // The background worker will pick it up later.
+            tracing::error!("failed to claim webhook delivery {delivery_id}: {e:?}");
+        }
+    }
+}
+
+/// Processes all deliveries whose previous attempt failed or was interrupted.
+pub async fn run_queued_webhooks(ctx: &Context, db: &DbClient) -> anyhow::Result<()> {
+    let deliveries = webhooks::claim_deliveries_to_process(db, WEBHOOK_MAX_ATTEMPTS).await?;
+    tracing::trace!("webhook deliveries to retry: {}", deliveries.len());
+
+    for delivery in deliveries {
+        let _guard = crate::issue_lock::lock_payload(&delivery.payload).await;
+        process_webhook_delivery(ctx, db, delivery).await;
+    }
+
+    let deleted = webhooks::delete_processed_deliveries(db, WEBHOOK_RETENTION_DAYS).await?;
+    if deleted > 0 {
+        tracing::debug!("deleted {deleted} processed webhook deliveries");
+    }
+
+    Ok(())
+}
+
+async fn process_webhook_delivery(ctx: &Context, db: &DbClient, delivery: WebhookDelivery) {
+    let WebhookDelivery {
+        delivery_id,
+        event,
+        payload,
+        attempts,
+        retry_handlers,
+        ..
+    } = delivery;
+    let event = match event.parse::<crate::EventName>() {
+        Ok(event) => event,
+        Err(infallible) => match infallible {},
+    };
+
+    let mut attempt = crate::WebhookAttempt {
+        handlers: retry_handlers,
+        ..Default::default()
+    };
+    let res = match crate::webhook(event, payload, ctx, &mut attempt).await {
+        Ok(_) => webhooks::mark_processed(db, &delivery_id, None, &attempt.handler_errors).await,
+        Err(e) if e.is_permanent() => {
+            let e = e.into_inner();
+            tracing::error!("webhook delivery {delivery_id} can't be processed: {e:?}");
+            webhooks::mark_processed(
+                db,
+                &delivery_id,
+                Some(format!("{e:?}").as_str()),
+                &attempt.handler_errors,
+            )
+            .await
+        }
+        Err(e) => {
+            let e = e.into_inner();
+            if attempts >= WEBHOOK_MAX_ATTEMPTS {
+                tracing::error!(
+                    "webhook delivery {delivery_id} failed {attempts} times, giving up: {e:?}"
+                );
+            } else {
+                tracing::warn!(
+                    "webhook delivery {delivery_id} failed (attempt {attempts}), will retry: {e:?}"
+                );
+            }
+            let next_attempt_at = Utc::now() + webhook_retry_delay(attempts);
+            webhooks::mark_failed(
+                db,
+                &delivery_id,
+                &format!("{e:?}"),
+                &attempt.handler_errors,
+                attempt.handlers.as_deref(),
+                &next_attempt_at,
+            )
+            .await
+        }
+    };
+    if let Err(e) = res {
+        tracing::error!("failed to update webhook delivery {delivery_id}: {e:?}");
+    }
+}
+
+/// Processes a stored delivery again, regardless of whether it was already
+/// processed, and returns a report of what happened.
+///
+/// This is intended for reproducing problems. The stored delivery is left
+/// unchanged. Pass a `Context` with a dry-run [`GithubClient`] to avoid
+/// modifying anything on GitHub.
+///
+/// [`GithubClient`]: crate::github::GithubClient
+pub async fn replay_webhook(
+    ctx: &Context,
+    db: &DbClient,
+    delivery_id: &str,
+) -> anyhow::Result<String> {
+    let delivery = webhooks::get_delivery(db, delivery_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("delivery {delivery_id} not found"))?;
+    let event = match delivery.event.parse::<crate::EventName>() {
+        Ok(event) => event,
+        Err(infallible) => match infallible {},
+    };
+    tracing::info!(
+        "replaying {} delivery {delivery_id} received at {}",
+        delivery.event,
+        delivery.received_at
+    );
+
+    let _guard = crate::issue_lock::lock_payload(&delivery.payload).await;
+    let mut attempt = crate::WebhookAttempt::default();
+    let mut report = match crate::webhook(event, delivery.payload, ctx, &mut attempt).await {
+        Ok(true) => String::from("processed"),
+        Ok(false) => String::from("ignored"),
+        Err(e) => format!("failed: {:?}", e.into_inner()),
+    };
+    for error in attempt.handler_errors {
+        report.push_str("\n\nhandler error: ");
+        report.push_str(&error);
+    }
+    Ok(report)
+}
+
+/// Exponential backoff between attempts: 30s, 1m, 2m, 4m, ...
+fn webhook_retry_delay(attempts: i32) -> chrono::Duration {
+    let exponent = attempts.clamp(1, 10) as u32 - 1;
+    chrono::Duration::seconds(30 * 2i64.pow(exponent))
+}
+
 static MIGRATIONS: &[&str] = &[
     "
 CREATE TABLE notifications (
@@ -273,4 +429,19 @@ CREATE UNIQUE INDEX jobs_name_scheduled_at_unique_index
         name, scheduled_at
     );
 ",
+    "
+CREATE TABLE webhook_deliveries (
+    delivery_id TEXT PRIMARY KEY,
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    received_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    processed_at TIMESTAMP WITH TIME ZONE,
+    error_message TEXT
+);
+",
+    "ALTER TABLE webhook_deliveries ADD COLUMN headers JSONB;",
+    "ALTER TABLE webhook_deliveries ADD COLUMN handler_errors TEXT[];",
+    "ALTER TABLE webhook_deliveries ADD COLUMN retry_handlers TEXT[];",
 ];
@@ -0,0 +1,208 @@
+//! The `webhook_deliveries` table stores incoming GitHub webhooks before they
+//! are processed.
+//!
+//! Deliveries are keyed by the `X-GitHub-Delivery` header, which GitHub keeps
+//! the same when redelivering a webhook, so duplicates can be detected.
+//! Deliveries which fail with an internal error are retried with a backoff,
+//! running only the handlers which failed.
+//!
+//! Processed deliveries are kept around for a while, together with their
+//! headers and any handler errors, so that problems can be reproduced by
+//! replaying them.
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, Utc};
+use tokio_postgres::Client as DbClient;
+
+#[derive(Debug)]
+pub struct WebhookDelivery {
+    pub delivery_id: String,
+    /// The value of the `X-GitHub-Event` header.
+    pub event: String,
+    pub payload: String,
+    /// All request headers, as a JSON object.
+    pub headers: serde_json::Value,
+    pub received_at: DateTime<Utc>,
+    /// How many times processing has been started, including the current one.
+    pub attempts: i32,
+    /// The handlers which failed in the previous attempt, or `None` if all
+    /// handlers should run.
+    pub retry_handlers: Option<Vec<String>>,
+}
+
+/// How long a claimed delivery is reserved for the process that claimed it.
+///
+/// If processing has not finished by then (for example, because triagebot
+/// crashed), the delivery is picked up again by the background worker.
+const CLAIM_INTERVAL: &str = "10 minutes";
+
+/// Records a new delivery.
+///
+/// Returns `false` if a delivery with the same ID was already recorded.
+pub async fn insert_delivery(
+    db: &DbClient,
+    delivery_id: &str,
+    event: &str,
+    headers: &serde_json::Value,
+    payload: &str,
+) -> Result<bool> {
+    tracing::trace!("insert_delivery(delivery_id={})", delivery_id);
+
+    let inserted = db
+        .execute(
+            "INSERT INTO webhook_deliveries (delivery_id, event, headers, payload)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (delivery_id) DO NOTHING",
+            &[&delivery_id, &event, &headers, &payload],
+        )
+        .await
+        .context("Inserting webhook delivery")?;
+
+    Ok(inserted == 1)
+}
+
+/// Claims the given delivery for processing.
+///
+/// Returns `None` if it has already been processed, or if it is currently
+/// claimed by someone else.
+pub async fn claim_delivery(db: &DbClient, delivery_id: &str) -> Result<Option<WebhookDelivery>> {
+    tracing::trace!("claim_delivery(delivery_id={})", delivery_id);
+
+    let row = db
+        .query_opt(
+            &format!(
+                "UPDATE webhook_deliveries
+                    SET attempts = attempts + 1, next_attempt_at = now() + INTERVAL '{CLAIM_INTERVAL}'
+                    WHERE delivery_id = $1 AND processed_at IS NULL AND next_attempt_at <= now()
+                    RETURNING delivery_id, event, payload, headers, received_at, attempts, retry_handlers"
+            ),
+            &[&delivery_id],
+        )
+        .await
+        .context("Claiming webhook delivery")?;
+
+    row.as_ref().map(deserialize_delivery).transpose()
+}
+
+/// Claims all deliveries which are due for a (re)try, oldest first.
+pub async fn claim_deliveries_to_process(
+    db: &DbClient,
+    max_attempts: i32,
+) -> Result<Vec<WebhookDelivery>> {
+    let rows = db
+        .query(
+            &format!(
+                "UPDATE webhook_deliveries
+                    SET attempts = attempts + 1, next_attempt_at = now() + INTERVAL '{CLAIM_INTERVAL}'
+                    WHERE delivery_id IN (
+                        SELECT delivery_id FROM webhook_deliveries
+                        WHERE processed_at IS NULL AND next_attempt_at <= now() AND attempts < $1
+                        ORDER BY received_at
+                        LIMIT 100
+                        FOR UPDATE SKIP LOCKED
+                    )
+                    RETURNING delivery_id, event, payload, headers, received_at, attempts, retry_handlers"
+            ),
+            &[&max_attempts],
+        )
+        .await
+        .context("Claiming webhook deliveries")?;
+
+    let mut deliveries = rows
+        .iter()
+        .map(deserialize_delivery)
+        .collect::<Result<Vec<_>>>()?;
+    deliveries.sort_by_key(|delivery| delivery.received_at);
+    Ok(deliveries)
+}
+
+/// Returns the given delivery, regardless of whether it has been processed.
+pub async fn get_delivery(db: &DbClient, delivery_id: &str) -> Result<Option<WebhookDelivery>> {
+    let row = db
+        .query_opt(
+            "SELECT delivery_id, event, payload, headers, received_at, attempts, retry_handlers
+                FROM webhook_deliveries WHERE delivery_id = $1",
+            &[&delivery_id],
+        )
+        .await
+        .context("Selecting webhook delivery")?;
+
+    row.as_ref().map(deserialize_delivery).transpose()
+}
+
+/// Records that the delivery doesn't need to be processed again.
+///
+/// `message` is the error which made processing give up, if any.
+pub async fn mark_processed(
+    db: &DbClient,
+    delivery_id: &str,
+    message: Option<&str>,
+    handler_errors: &[String],
+) -> Result<()> {
+    tracing::trace!("mark_processed(delivery_id={})", delivery_id);
+
+    db.execute(
+        "UPDATE webhook_deliveries
+            SET processed_at = now(), error_message = $2, handler_errors = $3
+            WHERE delivery_id = $1",
+        &[&delivery_id, &message, &handler_errors],
+    )
+    .await
+    .context("Marking webhook delivery processed")?;
+
+    Ok(())
+}
+
+/// Records a processing failure, and when and with which handlers the
+/// delivery should be retried.
+pub async fn mark_failed(
+    db: &DbClient,
+    delivery_id: &str,
+    message: &str,
+    handler_errors: &[String],
+    retry_handlers: Option<&[String]>,
+    next_attempt_at: &DateTime<Utc>,
+) -> Result<()> {
+    tracing::trace!("mark_failed(delivery_id={})", delivery_id);
+
+    db.execute(
+        "UPDATE webhook_deliveries
+            SET error_message = $2, handler_errors = $3, retry_handlers = $4, next_attempt_at = $5
+            WHERE delivery_id = $1",
+        &[
+            &delivery_id,
+            &message,
+            &handler_errors,
+            &retry_handlers,
+            &next_attempt_at,
+        ],
+    )
+    .await
+    .context("Marking webhook delivery failed")?;
+
+    Ok(())
+}
+
+/// Deletes processed deliveries older than the given number of days.
+pub async fn delete_processed_deliveries(db: &DbClient, days: i32) -> Result<u64> {
+    db.execute(
+        "DELETE FROM webhook_deliveries
+            WHERE processed_at IS NOT NULL AND processed_at < now() - make_interval(days => $1)",
+        &[&days],
+    )
+    .await
+    .context("Deleting processed webhook deliveries")
+}
+
+fn deserialize_delivery(row: &tokio_postgres::row::Row) -> Result<WebhookDelivery> {
+    Ok(WebhookDelivery {
+        delivery_id: row.try_get(0)?,
+        event: row.try_get(1)?,
+        payload: row.try_get(2)?,
+        headers: row
+            .try_get::<_, Option<serde_json::Value>>(3)?
+            .unwrap_or_default(),
+        received_at: row.try_get(4)?,
+        attempts: row.try_get(5)?,
+        retry_handlers: row.try_get(6)?,
+    })
+}
@@ -0,0 +1,66 @@
+//! Dry-run mode, in which triagebot records the changes it would make on
+//! GitHub and Zulip instead of making them.
+//!
+//! It is enabled for all repositories with `TRIAGEBOT_DRY_RUN=1`, or for one
+//! repository with `dry-run = true` in its `triagebot.toml`. The recorded
+//! actions are served as JSON from `/admin/dry-run`.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tracing as log;
+
+/// The number of actions to keep; older ones are dropped.
+const MAX_ACTIONS: usize = 1000;
+
+static ACTIONS: Lazy<Mutex<VecDeque<Action>>> = Lazy::new(Default::default);
+
+/// A request which was skipped because of dry-run mode.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Action {
+    pub time: DateTime<Utc>,
+    /// `github` or `zulip`.
+    pub service: &'static str,
+    pub method: String,
+    pub url: String,
+    /// The request body, if any.
+    pub body: Option<String>,
+}
+
+/// Whether dry-run mode is enabled for all repositories.
+pub fn enabled_globally() -> bool {
+    static ENABLED: Lazy<bool> = Lazy::new(|| {
+        std::env::var("TRIAGEBOT_DRY_RUN").map_or(false, |v| !v.is_empty() && v != "0")
+    });
+    *ENABLED
+}
+
+/// Records a request which is not sent.
+pub(crate) fn record(service: &'static str, req: &reqwest::Request) {
+    let action = Action {
+        time: Utc::now(),
+        service,
+        method: req.method().to_string(),
+        url: req.url().to_string(),
+        body: req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|body| String::from_utf8_lossy(body).into_owned()),
+    };
+    log::info!(
+        "dry run, skipping {service} request {} {}",
+        action.method,
+        action.url
+    );
+    let mut actions = ACTIONS.lock().unwrap();
+    if actions.len() >= MAX_ACTIONS {
+        actions.pop_front();
+    }
+    actions.push_back(action);
+}
+
+/// Returns the recorded actions, oldest first.
+pub fn actions() -> Vec<Action> {
+    ACTIONS.lock().unwrap().iter().cloned().collect()
+}
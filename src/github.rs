@@ -2,19 +2,133 @@ use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{DateTime, FixedOffset, Utc};
-use futures::{future::BoxFuture, FutureExt};
 use hyper::header::HeaderValue;
-use once_cell::sync::OnceCell;
+use once_cell::sync::{Lazy, OnceCell};
 use reqwest::header::{AUTHORIZATION, USER_AGENT};
 use reqwest::{Client, Request, RequestBuilder, Response, StatusCode};
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
-use std::{
-    fmt,
-    time::{Duration, SystemTime},
-};
+use std::fmt;
+use std::sync::Arc;
 use tracing as log;
 
+pub mod app;
+mod cache;
+pub(crate) mod retry;
+
+/// Reads a base URL from the given environment variable, without any
+/// trailing slash.
+pub(crate) fn base_url_from_env(var: &str, default: &str) -> String {
+    std::env::var(var)
+        .ok()
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| default.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// The base URL of the GitHub REST API.
+///
+/// This can be changed with `GITHUB_API_URL`, for example to
+/// `https://github.example.com/api/v3` for GitHub Enterprise.
+pub fn api_url() -> &'static str {
+    static URL: Lazy<String> =
+        Lazy::new(|| base_url_from_env("GITHUB_API_URL", "https://api.github.com"));
+    &URL
+}
+
+/// The URL of the GitHub GraphQL API.
+///
+/// This can be changed with `GITHUB_GRAPHQL_API_URL`, and defaults to
+/// `graphql` under [`api_url`] without a trailing `/v3`, so that GitHub
+/// Enterprise's `https://github.example.com/api/v3` gives
+/// `https://github.example.com/api/graphql`.
+pub fn graphql_url() -> &'static str {
+    static URL: Lazy<String> =
+        Lazy::new(|| base_url_from_env("GITHUB_GRAPHQL_API_URL", &default_graphql_url(api_url())));
+    &URL
+}
+
+fn default_graphql_url(api_url: &str) -> String {
+    format!("{}/graphql", api_url.strip_suffix("/v3").unwrap_or(api_url))
+}
+
+/// Whether a GraphQL document defines a mutation operation.
+///
+/// Only the keyword which starts each top-level definition is looked at, so
+/// field names, arguments and strings which happen to say "mutation" don't
+/// count.
+fn defines_mutation(document: &str) -> bool {
+    let mut chars = document.chars().peekable();
+    let mut depth = 0usize;
+    let mut at_definition = true;
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => {
+                chars.find(|&c| c == '\n' || c == '\r');
+            }
+            '"' => {
+                if chars.next_if_eq(&'"').is_some() {
+                    if chars.next_if_eq(&'"').is_some() {
+                        // A block string, which ends with `"""`.
+                        let mut quotes = 0;
+                        for c in chars.by_ref() {
+                            quotes = if c == '"' { quotes + 1 } else { 0 };
+                            if quotes == 3 {
+                                break;
+                            }
+                        }
+                    }
+                    // Otherwise, an empty string.
+                } else {
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '\\' => {
+                                chars.next();
+                            }
+                            '"' => break,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            '{' | '(' | '[' => {
+                // A definition starting with `{` is a query.
+                at_definition = false;
+                depth += 1;
+            }
+            '}' | ')' | ']' => {
+                depth = depth.saturating_sub(1);
+                at_definition = depth == 0 && c == '}';
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                if depth == 0 && at_definition {
+                    if word == "mutation" {
+                        return true;
+                    }
+                    at_definition = false;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// The base URL for raw file contents.
+///
+/// This can be changed with `GITHUB_RAW_URL`, for example to
+/// `https://github.example.com/raw` for GitHub Enterprise.
+pub fn raw_url() -> &'static str {
+    static URL: Lazy<String> =
+        Lazy::new(|| base_url_from_env("GITHUB_RAW_URL", "https://raw.githubusercontent.com"));
+    &URL
+}
+
 #[derive(Debug, PartialEq, Eq, serde::Deserialize)]
 pub struct User {
     pub login: String,
@@ -23,18 +137,61 @@ pub struct User {
 
 impl GithubClient {
     async fn send_req(&self, req: RequestBuilder) -> anyhow::Result<(Bytes, String)> {
-        const MAX_ATTEMPTS: usize = 2;
         log::debug!("send_req with {:?}", req);
         let req_dbg = format!("{:?}", req);
-        let req = req
+        let mut req = req
             .build()
             .with_context(|| format!("building reqwest {}", req_dbg))?;
 
-        let mut resp = self.client.execute(req.try_clone().unwrap()).await?;
-        if let Some(sleep) = Self::needs_retry(&resp).await {
-            resp = self.retry(req, sleep, MAX_ATTEMPTS).await?;
+        if self.dry_run && Self::is_write(&req) {
+            crate::dry_run::record("github", &req);
+            return Ok((Bytes::from_static(b"{}"), req_dbg));
+        }
+
+        let cache_key =
+            cache::ResponseCache::is_cacheable(&req).then(|| cache::CacheKey::new(&req));
+        let cached_body = match &cache_key {
+            Some(key) => self.cache.prepare(key, &mut req),
+            None => None,
+        };
+
+        let method = req.method().to_string();
+        let timer = crate::metrics::GITHUB_REQUEST_DURATION
+            .with_label_values(&[&method])
+            .start_timer();
+        // Only POST requests which modify something can't be repeated safely.
+        let idempotent = req.method() != reqwest::Method::POST || !Self::is_write(&req);
+        let mut attempt = 1;
+        let resp = loop {
+            let attempt_req = req
+                .try_clone()
+                .with_context(|| format!("request can't be retried {req_dbg}"))?;
+            let resp = self.client.execute(attempt_req).await?;
+            let Some(delay) =
+                retry::policy().delay(attempt, idempotent, resp.status(), resp.headers())
+            else {
+                break resp;
+            };
+            log::warn!(
+                "retrying in {delay:?} after {} (attempt {attempt}): {req_dbg}",
+                resp.status()
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+        timer.observe_duration();
+        crate::metrics::GITHUB_REQUESTS
+            .with_label_values(&[&method, resp.status().as_str()])
+            .inc();
+        Self::record_rate_limit(&resp);
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            if let Some(body) = cached_body {
+                self.cache.hit();
+                return Ok((body, req_dbg));
+            }
         }
         let maybe_err = resp.error_for_status_ref().err();
+        let headers = resp.headers().clone();
         let body = resp
             .bytes()
             .await
@@ -43,141 +200,95 @@ impl GithubClient {
             return Err(anyhow::Error::new(e))
                 .with_context(|| format!("response: {}", String::from_utf8_lossy(&body)));
         }
+        if let Some(key) = cache_key {
+            self.cache.store(&key, &headers, &body);
+        }
 
         Ok((body, req_dbg))
     }
 
-    async fn needs_retry(resp: &Response) -> Option<Duration> {
-        const REMAINING: &str = "X-RateLimit-Remaining";
-        const RESET: &str = "X-RateLimit-Reset";
-
-        if resp.status().is_success() {
-            return None;
-        }
-
-        let headers = resp.headers();
-        if !(headers.contains_key(REMAINING) && headers.contains_key(RESET)) {
-            return None;
+    /// Whether the request would modify something on GitHub.
+    ///
+    /// GraphQL queries are sent with POST, so those are only considered
+    /// writes if they define a mutation, or can't be read.
+    fn is_write(req: &Request) -> bool {
+        #[derive(serde::Deserialize)]
+        struct GraphqlRequest {
+            query: String,
         }
-
-        // Weird github api behavior. It asks us to retry but also has a remaining count above 1
-        // Try again immediately and hope for the best...
-        if headers[REMAINING] != "0" {
-            return Some(Duration::from_secs(0));
+        match *req.method() {
+            reqwest::Method::GET | reqwest::Method::HEAD => false,
+            reqwest::Method::POST if req.url().as_str() == graphql_url() => req
+                .body()
+                .and_then(|body| body.as_bytes())
+                .and_then(|body| serde_json::from_slice::<GraphqlRequest>(body).ok())
+                .map_or(true, |body| defines_mutation(&body.query)),
+            _ => true,
         }
-
-        let reset_time = headers[RESET].to_str().unwrap().parse::<u64>().unwrap();
-        Some(Duration::from_secs(Self::calc_sleep(reset_time) + 10))
     }
 
-    fn calc_sleep(reset_time: u64) -> u64 {
-        let epoch_time = SystemTime::UNIX_EPOCH.elapsed().unwrap().as_secs();
-        reset_time.saturating_sub(epoch_time)
+    /// Records the `X-RateLimit-Remaining` header of the response in the
+    /// metrics, if it is present.
+    fn record_rate_limit(resp: &Response) {
+        let headers = resp.headers();
+        let Some(remaining) = headers
+            .get("X-RateLimit-Remaining")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<i64>().ok())
+        else {
+            return;
+        };
+        let resource = headers
+            .get("X-RateLimit-Resource")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("core");
+        crate::metrics::GITHUB_RATE_LIMIT_REMAINING
+            .with_label_values(&[resource])
+            .set(remaining);
     }
 
-    fn retry(
-        &self,
-        req: Request,
-        sleep: Duration,
-        remaining_attempts: usize,
-    ) -> BoxFuture<Result<Response, reqwest::Error>> {
-        #[derive(Debug, serde::Deserialize)]
-        struct RateLimit {
-            #[allow(unused)]
-            pub limit: u64,
-            pub remaining: u64,
-            pub reset: u64,
-        }
-
-        #[derive(Debug, serde::Deserialize)]
-        struct RateLimitResponse {
-            pub resources: Resources,
-        }
-
-        #[derive(Debug, serde::Deserialize)]
-        struct Resources {
-            pub core: RateLimit,
-            pub search: RateLimit,
-            #[allow(unused)]
-            pub graphql: RateLimit,
-            #[allow(unused)]
-            pub source_import: RateLimit,
-        }
-
-        log::warn!(
-            "Retrying after {} seconds, remaining attepts {}",
-            sleep.as_secs(),
-            remaining_attempts,
-        );
-
-        async move {
-            tokio::time::sleep(sleep).await;
-
-            // check rate limit
-            let rate_resp = self
-                .client
-                .execute(
-                    self.client
-                        .get("https://api.github.com/rate_limit")
-                        .configure(self)
-                        .build()
-                        .unwrap(),
-                )
-                .await?;
-            rate_resp.error_for_status_ref()?;
-            let rate_limit_response = rate_resp.json::<RateLimitResponse>().await?;
-
-            // Check url for search path because github has different rate limits for the search api
-            let rate_limit = if req
-                .url()
-                .path_segments()
-                .map(|mut segments| matches!(segments.next(), Some("search")))
-                .unwrap_or(false)
-            {
-                rate_limit_response.resources.search
-            } else {
-                rate_limit_response.resources.core
-            };
-
-            // If we still don't have any more remaining attempts, try sleeping for the remaining
-            // period of time
-            if rate_limit.remaining == 0 {
-                let sleep = Self::calc_sleep(rate_limit.reset);
-                if sleep > 0 {
-                    tokio::time::sleep(Duration::from_secs(sleep)).await;
-                }
-            }
-
-            let resp = self.client.execute(req.try_clone().unwrap()).await?;
-            if let Some(sleep) = Self::needs_retry(&resp).await {
-                if remaining_attempts > 0 {
-                    return self.retry(req, sleep, remaining_attempts - 1).await;
-                }
-            }
-
-            Ok(resp)
-        }
-        .boxed()
+    pub async fn json<T>(&self, req: RequestBuilder) -> anyhow::Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let (body, _req_dbg) = self.send_req(req).await?;
+        Ok(serde_json::from_slice(&body)?)
     }
 
-    pub async fn json<T>(&self, req: RequestBuilder) -> anyhow::Result<T>
+    /// Like [`GithubClient::json`], for writes whose response is needed.
+    ///
+    /// In dry-run mode the request is skipped, and `dry_run` builds a
+    /// stand-in for the response from what the request asked for.
+    async fn write_json<T>(
+        &self,
+        req: RequestBuilder,
+        dry_run: impl FnOnce() -> T,
+    ) -> anyhow::Result<T>
     where
         T: serde::de::DeserializeOwned,
     {
         let (body, _req_dbg) = self.send_req(req).await?;
+        if self.dry_run {
+            return Ok(dry_run());
+        }
         Ok(serde_json::from_slice(&body)?)
     }
 }
 
+/// The SHA of the objects returned in dry-run mode, which are never created.
+const DRY_RUN_SHA: &str = "0000000000000000000000000000000000000000";
+
 impl User {
     pub async fn current(client: &GithubClient) -> anyhow::Result<Self> {
-        client.json(client.get("https://api.github.com/user")).await
+        client
+            .json(client.get(&format!("{}/user", api_url())))
+            .await
     }
 
     pub async fn is_team_member<'a>(&'a self, client: &'a GithubClient) -> anyhow::Result<bool> {
         log::trace!("Getting team membership for {:?}", self.login);
         let permission = crate::team_data::teams(client).await?;
-        let map = permission.teams;
+        let map = &permission.teams;
         let is_triager = map
             .get("wg-triage")
             .map_or(false, |w| w.members.iter().any(|g| g.github == self.login));
@@ -202,7 +313,7 @@ impl User {
     // Returns the ID of the given user, if the user is in the `all` team.
     pub async fn get_id<'a>(&'a self, client: &'a GithubClient) -> anyhow::Result<Option<usize>> {
         let permission = crate::team_data::teams(client).await?;
-        let map = permission.teams;
+        let map = &permission.teams;
         Ok(map["all"]
             .members
             .iter()
@@ -216,8 +327,7 @@ pub async fn get_team(
     team: &str,
 ) -> anyhow::Result<Option<rust_team_data::v1::Team>> {
     let permission = crate::team_data::teams(client).await?;
-    let mut map = permission.teams;
-    Ok(map.swap_remove(team))
+    Ok(permission.teams.get(team).cloned())
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, serde::Deserialize)]
@@ -230,9 +340,20 @@ pub struct Label {
 /// Some webhook events include a `pull_request` field in the Issue object,
 /// and some don't. GitHub does include a few fields here, but they aren't
 /// needed at this time (merged_at, diff_url, html_url, patch_url, url).
-#[derive(Debug, serde::Deserialize)]
+///
+/// It also holds information about the pull request which is fetched on
+/// demand, so that all the handlers processing an event share a single
+/// request for it.
+#[derive(Debug, Default, serde::Deserialize)]
 pub struct PullRequestDetails {
-    // none for now
+    #[serde(skip)]
+    diff: tokio::sync::OnceCell<String>,
+    #[serde(skip)]
+    files: tokio::sync::OnceCell<Vec<PullRequestFile>>,
+    #[serde(skip)]
+    commits: tokio::sync::OnceCell<Vec<GithubCommit>>,
+    #[serde(skip)]
+    new_contributor: tokio::sync::OnceCell<bool>,
 }
 
 /// An issue or pull request.
@@ -398,8 +519,10 @@ impl fmt::Display for IssueRepository {
 impl IssueRepository {
     fn url(&self) -> String {
         format!(
-            "https://api.github.com/repos/{}/{}",
-            self.organization, self.repository
+            "{}/repos/{}/{}",
+            api_url(),
+            self.organization,
+            self.repository
         )
     }
 
@@ -692,10 +815,16 @@ impl Issue {
             assignees: &'a [&'a str],
         }
 
-        let result: Issue = client
-            .json(client.post(&url).json(&AssigneeReq { assignees: &[user] }))
+        let (body, _req_dbg) = client
+            .send_req(client.post(&url).json(&AssigneeReq { assignees: &[user] }))
             .await
             .map_err(AssignmentError::Http)?;
+        if client.is_dry_run() {
+            // Nobody was assigned, so there is nothing to check.
+            return Ok(());
+        }
+        let result: Issue =
+            serde_json::from_slice(&body).map_err(|e| AssignmentError::Http(e.into()))?;
         // Invalid assignees are silently ignored. We can just check if the user is now
         // contained in the assignees list.
         let success = result
@@ -786,63 +915,128 @@ impl Issue {
     }
 
     /// Returns the diff in this event, for Open and Synchronize events for now.
-    pub async fn diff(&self, client: &GithubClient) -> anyhow::Result<Option<String>> {
-        let (before, after) = if let (Some(base), Some(head)) = (&self.base, &self.head) {
-            (base.sha.clone(), head.sha.clone())
-        } else {
+    ///
+    /// The diff is only fetched once per event.
+    pub async fn diff(&self, client: &GithubClient) -> anyhow::Result<Option<&str>> {
+        let Some(pr) = &self.pull_request else {
+            return Ok(None);
+        };
+        let (Some(base), Some(head)) = (&self.base, &self.head) else {
             return Ok(None);
         };
 
-        let mut req = client.get(&format!(
-            "{}/compare/{}...{}",
-            self.repository().url(),
-            before,
-            after
-        ));
-        req = req.header("Accept", "application/vnd.github.v3.diff");
-        let (diff, _) = client.send_req(req).await?;
-        let body = String::from_utf8_lossy(&diff).to_string();
-        Ok(Some(body))
+        let diff = pr
+            .diff
+            .get_or_try_init(|| async {
+                let mut req = client.get(&format!(
+                    "{}/compare/{}...{}",
+                    self.repository().url(),
+                    base.sha,
+                    head.sha
+                ));
+                req = req.header("Accept", "application/vnd.github.v3.diff");
+                let (diff, _) = client.send_req(req).await?;
+                anyhow::Ok(String::from_utf8_lossy(&diff).to_string())
+            })
+            .await?;
+        Ok(Some(diff))
     }
 
     /// Returns the commits from this pull request (no commits are returned if this `Issue` is not
     /// a pull request).
-    pub async fn commits(&self, client: &GithubClient) -> anyhow::Result<Vec<GithubCommit>> {
-        if !self.is_pr() {
-            return Ok(vec![]);
-        }
+    ///
+    /// The commits are only fetched once per event.
+    pub async fn commits(&self, client: &GithubClient) -> anyhow::Result<&[GithubCommit]> {
+        let Some(pr) = &self.pull_request else {
+            return Ok(&[]);
+        };
 
-        let mut commits = Vec::new();
-        let mut page = 1;
-        loop {
-            let req = client.get(&format!(
-                "{}/pulls/{}/commits?page={page}&per_page=100",
-                self.repository().url(),
-                self.number
-            ));
-
-            let new: Vec<_> = client.json(req).await?;
-            if new.is_empty() {
-                break;
-            }
-            commits.extend(new);
+        let commits = pr
+            .commits
+            .get_or_try_init(|| async {
+                let mut commits = Vec::new();
+                let mut page = 1;
+                loop {
+                    let req = client.get(&format!(
+                        "{}/pulls/{}/commits?page={page}&per_page=100",
+                        self.repository().url(),
+                        self.number
+                    ));
+
+                    let new: Vec<_> = client.json(req).await?;
+                    if new.is_empty() {
+                        break;
+                    }
+                    commits.extend(new);
 
-            page += 1;
-        }
+                    page += 1;
+                }
+                anyhow::Ok(commits)
+            })
+            .await?;
         Ok(commits)
     }
 
-    pub async fn files(&self, client: &GithubClient) -> anyhow::Result<Vec<PullRequestFile>> {
+    /// Returns whether the author of this pull request has not contributed
+    /// to `repo` before. See [`GithubClient::is_new_contributor`].
+    ///
+    /// This is only checked once per event.
+    pub async fn is_new_contributor(&self, client: &GithubClient, repo: &Repository) -> bool {
+        let Some(pr) = &self.pull_request else {
+            return client.is_new_contributor(repo, &self.user.login).await;
+        };
+        *pr.new_contributor
+            .get_or_init(|| client.is_new_contributor(repo, &self.user.login))
+            .await
+    }
+
+    /// Returns the branch this pull request targets, or `None` if this is
+    /// not a pull request.
+    ///
+    /// Only `pull_request` events include the base, so for other events it is
+    /// fetched.
+    pub async fn base_ref(&self, client: &GithubClient) -> anyhow::Result<Option<String>> {
         if !self.is_pr() {
-            return Ok(vec![]);
+            return Ok(None);
+        }
+        if let Some(base) = &self.base {
+            return Ok(Some(base.git_ref.clone()));
         }
 
+        #[derive(serde::Deserialize)]
+        struct PullRequest {
+            base: CommitBase,
+        }
         let req = client.get(&format!(
-            "{}/pulls/{}/files",
+            "{}/pulls/{}",
             self.repository().url(),
             self.number
         ));
-        Ok(client.json(req).await?)
+        let pr: PullRequest = client.json(req).await?;
+        Ok(Some(pr.base.git_ref))
+    }
+
+    /// Returns the files changed by this pull request (no files are returned
+    /// if this `Issue` is not a pull request).
+    ///
+    /// The files are only fetched once per event.
+    pub async fn files(&self, client: &GithubClient) -> anyhow::Result<&[PullRequestFile]> {
+        let Some(pr) = &self.pull_request else {
+            return Ok(&[]);
+        };
+
+        let files = pr
+            .files
+            .get_or_try_init(|| async {
+                let req = client.get(&format!(
+                    "{}/pulls/{}/files",
+                    self.repository().url(),
+                    self.number
+                ));
+                client.json(req).await
+            })
+            .await?;
+        Ok(files)
     }
 }
 
@@ -976,7 +1170,7 @@ struct PullRequestEventFields {}
 
 #[derive(Clone, Debug, serde::Deserialize)]
 pub struct CommitBase {
-    sha: String,
+    pub sha: String,
     #[serde(rename = "ref")]
     pub git_ref: String,
     pub repo: Repository,
@@ -1022,11 +1216,8 @@ struct Ordering<'a> {
 }
 
 impl Repository {
-    const GITHUB_API_URL: &'static str = "https://api.github.com";
-    const GITHUB_GRAPHQL_API_URL: &'static str = "https://api.github.com/graphql";
-
     fn url(&self) -> String {
-        format!("{}/repos/{}", Repository::GITHUB_API_URL, self.full_name)
+        format!("{}/repos/{}", api_url(), self.full_name)
     }
 
     pub fn owner(&self) -> &str {
@@ -1161,7 +1352,7 @@ impl Repository {
             .join("&");
         format!(
             "{}/repos/{}/{}?{}",
-            Repository::GITHUB_API_URL,
+            api_url(),
             self.full_name,
             endpoint,
             filters
@@ -1194,7 +1385,7 @@ impl Repository {
             .join("+");
         format!(
             "{}/search/issues?q={}&sort={}&order={}&per_page={}&page={}",
-            Repository::GITHUB_API_URL,
+            api_url(),
             filters,
             ordering.sort,
             ordering.direction,
@@ -1221,12 +1412,22 @@ impl Repository {
         tree: &str,
     ) -> anyhow::Result<GitCommit> {
         let url = format!("{}/git/commits", self.url());
+        let req = client.post(&url).json(&serde_json::json!({
+            "message": message,
+            "parents": parents,
+            "tree": tree,
+        }));
         client
-            .json(client.post(&url).json(&serde_json::json!({
-                "message": message,
-                "parents": parents,
-                "tree": tree,
-            })))
+            .write_json(req, || GitCommit {
+                sha: DRY_RUN_SHA.to_string(),
+                author: GitUser {
+                    date: Utc::now().into(),
+                },
+                message: message.to_string(),
+                tree: GitCommitTree {
+                    sha: tree.to_string(),
+                },
+            })
             .await
             .with_context(|| format!("{} failed to create commit for tree {tree}", self.full_name))
     }
@@ -1252,11 +1453,19 @@ impl Repository {
         sha: &str,
     ) -> anyhow::Result<GitReference> {
         let url = format!("{}/git/refs/{}", self.url(), refname);
+        let req = client.patch(&url).json(&serde_json::json!({
+            "sha": sha,
+            "force": true,
+        }));
         client
-            .json(client.patch(&url).json(&serde_json::json!({
-                "sha": sha,
-                "force": true,
-            })))
+            .write_json(req, || GitReference {
+                refname: format!("refs/{refname}"),
+                object: GitObject {
+                    object_type: "commit".to_string(),
+                    sha: sha.to_string(),
+                    url: format!("{}/git/commits/{sha}", self.url()),
+                },
+            })
             .await
             .with_context(|| {
                 format!(
@@ -1302,7 +1511,7 @@ impl Repository {
             let query = RecentCommits::build(args.clone());
             let data = client
                 .json::<cynic::GraphQlResponse<RecentCommits>>(
-                    client.post(Repository::GITHUB_GRAPHQL_API_URL).json(&query),
+                    client.post(graphql_url()).json(&query),
                 )
                 .await
                 .with_context(|| {
@@ -1442,11 +1651,14 @@ impl Repository {
         tree: &[GitTreeEntry],
     ) -> anyhow::Result<GitTreeObject> {
         let url = format!("{}/git/trees", self.url());
+        let req = client.post(&url).json(&serde_json::json!({
+            "base_tree": base_tree,
+            "tree": tree,
+        }));
         client
-            .json(client.post(&url).json(&serde_json::json!({
-                "base_tree": base_tree,
-                "tree": tree,
-            })))
+            .write_json(req, || GitTreeObject {
+                sha: DRY_RUN_SHA.to_string(),
+            })
             .await
             .with_context(|| {
                 format!(
@@ -1489,13 +1701,36 @@ impl Repository {
         body: &str,
     ) -> anyhow::Result<Issue> {
         let url = format!("{}/pulls", self.url());
+        let req = client.post(&url).json(&serde_json::json!({
+            "title": title,
+            "head": head,
+            "base": base,
+            "body": body,
+        }));
         let mut issue: Issue = client
-            .json(client.post(&url).json(&serde_json::json!({
-                "title": title,
-                "head": head,
-                "base": base,
-                "body": body,
-            })))
+            .write_json(req, || Issue {
+                number: 0,
+                body: body.to_string(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                merge_commit_sha: None,
+                title: title.to_string(),
+                html_url: format!("https://github.com/{}/pull/0", self.full_name),
+                user: User {
+                    login: String::new(),
+                    id: None,
+                },
+                labels: Vec::new(),
+                assignees: Vec::new(),
+                pull_request: None,
+                merged: false,
+                draft: false,
+                comments_url: format!("{}/issues/0/comments", self.url()),
+                repository: OnceCell::new(),
+                base: None,
+                head: None,
+                state: IssueState::Open,
+            })
             .await
             .with_context(|| {
                 format!(
@@ -1503,7 +1738,7 @@ impl Repository {
                     self.full_name
                 )
             })?;
-        issue.pull_request = Some(PullRequestDetails {});
+        issue.pull_request = Some(PullRequestDetails::default());
         Ok(issue)
     }
 
@@ -1709,6 +1944,50 @@ pub struct PushEvent {
     pub git_ref: String,
     repository: Repository,
     sender: User,
+    /// The pushed commits. GitHub includes at most 2048 of them.
+    #[serde(default)]
+    pub commits: Vec<PushCommit>,
+}
+
+impl PushEvent {
+    /// Whether this is a push to the repository's default branch.
+    pub fn is_default_branch(&self) -> bool {
+        self.git_ref == format!("refs/heads/{}", self.repository.default_branch)
+    }
+
+    /// Whether the push may have added, modified or removed the given file.
+    ///
+    /// This errs on the side of `true` for large pushes, see
+    /// [`PUSH_EVENT_MAX_COMMITS`].
+    pub fn may_touch(&self, path: &str) -> bool {
+        self.commits.len() >= PUSH_EVENT_MAX_COMMITS
+            || self.commits.iter().any(|commit| {
+                commit
+                    .added
+                    .iter()
+                    .chain(&commit.modified)
+                    .chain(&commit.removed)
+                    .any(|p| p == path)
+            })
+    }
+}
+
+/// The number of commits from which a push is assumed to touch every file.
+///
+/// Push webhook payloads list up to 2048 commits; 20 is the limit of push
+/// events in the Events API. The lower threshold is kept to stay on the safe
+/// side: missing a change (e.g. keeping a stale `triagebot.toml`) costs more
+/// than refetching a file after one of the rare large pushes.
+const PUSH_EVENT_MAX_COMMITS: usize = 20;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PushCommit {
+    #[serde(default)]
+    pub added: Vec<String>,
+    #[serde(default)]
+    pub modified: Vec<String>,
+    #[serde(default)]
+    pub removed: Vec<String>,
 }
 
 /// An event triggered by a webhook.
@@ -1805,10 +2084,13 @@ trait RequestSend: Sized {
 
 impl RequestSend for RequestBuilder {
     fn configure(self, g: &GithubClient) -> RequestBuilder {
+        let req = self.header(USER_AGENT, "rust-lang-triagebot");
+        if g.token.is_empty() {
+            return req;
+        }
         let mut auth = HeaderValue::from_maybe_shared(format!("token {}", g.token)).unwrap();
         auth.set_sensitive(true);
-        self.header(USER_AGENT, "rust-lang-triagebot")
-            .header(AUTHORIZATION, &auth)
+        req.header(AUTHORIZATION, &auth)
     }
 }
 
@@ -1845,17 +2127,98 @@ fn get_token_from_git_config() -> anyhow::Result<String> {
 pub struct GithubClient {
     token: String,
     client: Client,
+    dry_run: bool,
+    app: Option<Arc<app::GithubApp>>,
+    cache: Arc<cache::ResponseCache>,
 }
 
 impl GithubClient {
     pub fn new(client: Client, token: String) -> Self {
-        GithubClient { client, token }
+        GithubClient {
+            client,
+            token,
+            dry_run: crate::dry_run::enabled_globally(),
+            app: None,
+            cache: Arc::new(cache::ResponseCache::new()),
+        }
+    }
+
+    /// Returns a client which records requests that would modify something on
+    /// GitHub or Zulip instead of sending them (see [`crate::dry_run`]).
+    ///
+    /// Skipped requests return an empty JSON object, so methods which need
+    /// the response of a write must check [`GithubClient::is_dry_run`] or
+    /// provide a stand-in response.
+    /// Requests made through octocrab or `raw()` are not affected, and the
+    /// handlers which use them don't run in dry-run mode.
+    ///
+    /// All clients are in dry-run mode if `TRIAGEBOT_DRY_RUN` is set.
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Whether this client is in dry-run mode.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
     }
 
     pub fn new_with_default_token(client: Client) -> Self {
         Self::new(client, default_token_from_env())
     }
 
+    /// Creates a client which authenticates as a GitHub App if one is
+    /// configured (see [`app::GithubApp::from_env`]), and with the default
+    /// token otherwise.
+    ///
+    /// With a GitHub App, the client itself still uses `GITHUB_API_TOKEN`,
+    /// for requests which are not tied to a repository, so it must be set.
+    /// Use [`GithubClient::installation_token`] to get a token for a
+    /// repository.
+    pub fn new_from_env(client: Client) -> anyhow::Result<Self> {
+        match app::GithubApp::from_env(client.clone())? {
+            Some(app) => {
+                let token = std::env::var("GITHUB_API_TOKEN").context(
+                    "GITHUB_API_TOKEN must be set when running as a GitHub App, \
+                     for requests which are not tied to a repository",
+                )?;
+                Ok(GithubClient {
+                    app: Some(Arc::new(app)),
+                    ..Self::new(client, token)
+                })
+            }
+            None => Ok(Self::new_with_default_token(client)),
+        }
+    }
+
+    /// Returns the installation access token for the given repository
+    /// (`owner/name`) if this client authenticates as a GitHub App.
+    pub async fn installation_token(&self, repo: &str) -> anyhow::Result<Option<String>> {
+        match &self.app {
+            Some(app) => Ok(Some(app.token_for_repo(repo).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Builds an octocrab client which uses the same token as this client.
+    pub fn octocrab(&self) -> anyhow::Result<octocrab::Octocrab> {
+        let mut builder = octocrab::OctocrabBuilder::new()
+            .base_uri(api_url())
+            .context("invalid GITHUB_API_URL")?;
+        if !self.token.is_empty() {
+            builder = builder.personal_token(self.token.clone());
+        }
+        builder.build().context("failed to build octocrab")
+    }
+
+    /// Returns a copy of this client which uses the given token.
+    pub fn with_token(&self, token: String) -> Self {
+        GithubClient {
+            token,
+            ..self.clone()
+        }
+    }
+
     pub fn raw(&self) -> &Client {
         &self.client
     }
@@ -1866,23 +2229,32 @@ impl GithubClient {
         branch: &str,
         path: &str,
     ) -> anyhow::Result<Option<Bytes>> {
-        let url = format!(
-            "https://raw.githubusercontent.com/{}/{}/{}",
-            repo, branch, path
-        );
+        let url = format!("{}/{}/{}/{}", raw_url(), repo, branch, path);
         let req = self.get(&url);
         let req_dbg = format!("{:?}", req);
-        let req = req
+        let mut req = req
             .build()
             .with_context(|| format!("failed to build request {:?}", req_dbg))?;
+        let cache_key = cache::CacheKey::new(&req);
+        let cached_body = self.cache.prepare(&cache_key, &mut req);
         let resp = self.client.execute(req).await.context(req_dbg.clone())?;
         let status = resp.status();
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(body) = cached_body {
+                self.cache.hit();
+                return Ok(Some(body));
+            }
+        }
+        let headers = resp.headers().clone();
         let body = resp
             .bytes()
             .await
             .with_context(|| format!("failed to read response body {req_dbg}"))?;
         match status {
-            StatusCode::OK => Ok(Some(body)),
+            StatusCode::OK => {
+                self.cache.store(&cache_key, &headers, &body);
+                Ok(Some(body))
+            }
             StatusCode::NOT_FOUND => Ok(None),
             status => anyhow::bail!("failed to GET {}: {}", url, status),
         }
@@ -1931,7 +2303,8 @@ impl GithubClient {
 
     pub async fn rust_commit(&self, sha: &str) -> Option<GithubCommit> {
         let req = self.get(&format!(
-            "https://api.github.com/repos/rust-lang/rust/commits/{}",
+            "{}/repos/rust-lang/rust/commits/{}",
+            api_url(),
             sha
         ));
         match self.json(req).await {
@@ -1945,7 +2318,10 @@ impl GithubClient {
 
     /// This does not retrieve all of them, only the last several.
     pub async fn bors_commits(&self) -> Vec<GithubCommit> {
-        let req = self.get("https://api.github.com/repos/rust-lang/rust/commits?author=bors");
+        let req = self.get(&format!(
+            "{}/repos/rust-lang/rust/commits?author=bors",
+            api_url()
+        ));
         match self.json(req).await {
             Ok(r) => r,
             Err(e) => {
@@ -1961,14 +2337,29 @@ impl GithubClient {
         query: &str,
         vars: serde_json::Value,
     ) -> anyhow::Result<T> {
-        self.json(
-            self.post(Repository::GITHUB_GRAPHQL_API_URL)
-                .json(&serde_json::json!({
+        let mut attempt = 1;
+        loop {
+            let resp: serde_json::Value = self
+                .json(self.post(graphql_url()).json(&serde_json::json!({
                     "query": query,
                     "variables": vars,
-                })),
-        )
-        .await
+                })))
+                .await?;
+            // Secondary rate limits are reported as errors in a successful
+            // response.
+            let rate_limited = resp["errors"].as_array().map_or(false, |errors| {
+                errors.iter().any(|error| error["type"] == "RATE_LIMITED")
+            });
+            if rate_limited {
+                if let Some(delay) = retry::policy().rate_limited_delay(attempt) {
+                    log::warn!("GraphQL query rate limited, retrying in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return Ok(serde_json::from_value(resp)?);
+        }
     }
 
     /// Returns the object ID of the given user.
@@ -2078,7 +2469,7 @@ impl GithubClient {
     ///
     /// The `full_name` should be something like `rust-lang/rust`.
     pub async fn repository(&self, full_name: &str) -> anyhow::Result<Repository> {
-        let req = self.get(&format!("{}/repos/{full_name}", Repository::GITHUB_API_URL));
+        let req = self.get(&format!("{}/repos/{full_name}", api_url()));
         self.json(req)
             .await
             .with_context(|| format!("{} failed to get repo", full_name))
@@ -2179,7 +2570,7 @@ impl IssuesQuery for LeastRecentlyReviewedPullRequests {
         };
         loop {
             let query = queries::LeastRecentlyReviewedPullRequests::build(args.clone());
-            let req = client.post(Repository::GITHUB_GRAPHQL_API_URL);
+            let req = client.post(graphql_url());
             let req = req.json(&query);
 
             let data: cynic::GraphQlResponse<queries::LeastRecentlyReviewedPullRequests> =
@@ -2314,7 +2705,7 @@ async fn project_items_by_status(
     let mut all_items = vec![];
     loop {
         let query = project_items::Query::build(args.clone());
-        let req = client.post(Repository::GITHUB_GRAPHQL_API_URL);
+        let req = client.post(graphql_url());
         let req = req.json(&query);
 
         let data: cynic::GraphQlResponse<project_items::Query> = client.json(req).await?;
@@ -2453,6 +2844,72 @@ impl Submodule {
 mod tests {
     use super::*;
 
+    #[test]
+    fn graphql_url_defaults() {
+        assert_eq!(
+            default_graphql_url("https://api.github.com"),
+            "https://api.github.com/graphql"
+        );
+        assert_eq!(
+            default_graphql_url("https://github.example.com/api/v3"),
+            "https://github.example.com/api/graphql"
+        );
+    }
+
+    #[test]
+    fn graphql_mutations() {
+        assert!(defines_mutation("mutation { addComment { id } }"));
+        assert!(defines_mutation(
+            "query Q { viewer { login } }\n# comment\nmutation M($id: ID!) { a(id: $id) }"
+        ));
+        assert!(!defines_mutation("{ repository { mutation } }"));
+        assert!(!defines_mutation(
+            "query($q: String = \"mutation {\") { search(query: $q) { mutation } }"
+        ));
+        assert!(!defines_mutation(
+            "# mutation\nquery { a(b: \"\"\"mutation \"\" }\"\"\") }"
+        ));
+        assert!(!defines_mutation(
+            "fragment mutation on Issue { id } query { ...mutation }"
+        ));
+    }
+
+    #[tokio::test]
+    async fn dry_run_docs_update_writes() {
+        let client = GithubClient::new(Client::new(), String::new()).dry_run();
+        let repo = Repository {
+            full_name: "rust-lang/dry-run-docs".to_string(),
+            default_branch: "master".to_string(),
+            fork: false,
+            parent: None,
+        };
+
+        let tree = repo.update_tree(&client, "base", &[]).await.unwrap();
+        let commit = repo
+            .create_commit(&client, "Update books", &["parent"], &tree.sha)
+            .await
+            .unwrap();
+        assert_eq!(commit.tree.sha, tree.sha);
+        let reference = repo
+            .update_reference(&client, "heads/docs-update", &commit.sha)
+            .await
+            .unwrap();
+        assert_eq!(reference.object.sha, commit.sha);
+        let pr = repo
+            .new_pr(&client, "Update books", "rustbot:docs-update", "master", "")
+            .await
+            .unwrap();
+        assert!(pr.is_pr());
+        assert_eq!(pr.title, "Update books");
+
+        let actions: Vec<_> = crate::dry_run::actions()
+            .into_iter()
+            .filter(|a| a.url.contains("/repos/rust-lang/dry-run-docs/"))
+            .map(|a| a.method)
+            .collect();
+        assert_eq!(actions, ["POST", "POST", "PATCH", "POST"]);
+    }
+
     #[test]
     fn display_labels() {
         let x = UnknownLabels {
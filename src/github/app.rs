@@ -0,0 +1,249 @@
+//! Authentication as a GitHub App.
+//!
+//! When `GITHUB_APP_ID` and `GITHUB_APP_PRIVATE_KEY` are set, requests made
+//! on behalf of a webhook event use an installation access token for the
+//! installation covering the event's repository. The app itself
+//! authenticates with a short-lived JWT signed with its private key, which is
+//! only used to look up installations and create installation tokens.
+//!
+//! Installation tokens are valid for an hour; they are cached and refreshed
+//! shortly before they expire.
+
+use anyhow::Context as _;
+use chrono::{DateTime, Duration, Utc};
+use openssl::{
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    sign::Signer,
+};
+use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use reqwest::{Client, RequestBuilder};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing as log;
+
+/// Tokens expiring sooner than this many minutes are refreshed.
+const REFRESH_BEFORE_EXPIRY_MINUTES: i64 = 5;
+
+pub struct GithubApp {
+    app_id: u64,
+    key: PKey<Private>,
+    client: Client,
+    /// Installation ID for each repository (`owner/name`) seen so far.
+    installations: Mutex<HashMap<String, u64>>,
+    /// Cached access token for each installation ID.
+    tokens: Mutex<HashMap<u64, InstallationToken>>,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(serde::Deserialize)]
+struct Installation {
+    id: u64,
+}
+
+impl GithubApp {
+    pub fn new(client: Client, app_id: u64, private_key_pem: &[u8]) -> anyhow::Result<Self> {
+        let key = PKey::private_key_from_pem(private_key_pem)
+            .context("failed to parse GitHub App private key")?;
+        Ok(GithubApp {
+            app_id,
+            key,
+            client,
+            installations: Mutex::new(HashMap::new()),
+            tokens: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Loads the app configuration from `GITHUB_APP_ID` and
+    /// `GITHUB_APP_PRIVATE_KEY` (the PEM-encoded private key).
+    ///
+    /// Returns `None` if the app is not configured.
+    pub fn from_env(client: Client) -> anyhow::Result<Option<Self>> {
+        let (Ok(app_id), Ok(key)) = (
+            std::env::var("GITHUB_APP_ID"),
+            std::env::var("GITHUB_APP_PRIVATE_KEY"),
+        ) else {
+            return Ok(None);
+        };
+        let app_id = app_id
+            .trim()
+            .parse()
+            .context("GITHUB_APP_ID must be a number")?;
+        Ok(Some(Self::new(client, app_id, key.as_bytes())?))
+    }
+
+    /// Returns an installation access token for the given repository
+    /// (`owner/name`).
+    pub async fn token_for_repo(&self, repo: &str) -> anyhow::Result<String> {
+        let installation_id = self.installation_id(repo).await?;
+        match self.installation_token(installation_id).await {
+            Err(e) if is_gone(&e) => {
+                // The app was uninstalled or suspended, or the repository
+                // moved to another installation; look it up again.
+                log::info!("installation {installation_id} is gone, evicting it");
+                self.installations
+                    .lock()
+                    .unwrap()
+                    .retain(|_, id| *id != installation_id);
+                self.tokens.lock().unwrap().remove(&installation_id);
+                let installation_id = self.installation_id(repo).await?;
+                self.installation_token(installation_id).await
+            }
+            result => result,
+        }
+    }
+
+    async fn installation_id(&self, repo: &str) -> anyhow::Result<u64> {
+        let cached = self.installations.lock().unwrap().get(repo).copied();
+        if let Some(id) = cached {
+            return Ok(id);
+        }
+        let installation: Installation = self
+            .app_request(
+                self.client
+                    .get(format!("{}/repos/{repo}/installation", super::api_url())),
+            )
+            .await
+            .with_context(|| format!("failed to find installation for {repo}"))?;
+        log::debug!("{repo} is covered by installation {}", installation.id);
+        self.installations
+            .lock()
+            .unwrap()
+            .insert(repo.to_string(), installation.id);
+        Ok(installation.id)
+    }
+
+    async fn installation_token(&self, installation_id: u64) -> anyhow::Result<String> {
+        let cached = self.tokens.lock().unwrap().get(&installation_id).cloned();
+        if let Some(token) = cached {
+            if token.expires_at - Duration::minutes(REFRESH_BEFORE_EXPIRY_MINUTES) > Utc::now() {
+                return Ok(token.token);
+            }
+        }
+        log::debug!("refreshing token for installation {installation_id}");
+        let token: InstallationToken = self
+            .app_request(self.client.post(format!(
+                "{}/app/installations/{installation_id}/access_tokens",
+                super::api_url()
+            )))
+            .await
+            .with_context(|| {
+                format!("failed to create token for installation {installation_id}")
+            })?;
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(installation_id, token.clone());
+        Ok(token.token)
+    }
+
+    /// Sends a request authenticated as the app itself.
+    async fn app_request<T: serde::de::DeserializeOwned>(
+        &self,
+        req: RequestBuilder,
+    ) -> anyhow::Result<T> {
+        let jwt = self.jwt(Utc::now())?;
+        let resp = req
+            .header(USER_AGENT, "rust-lang-triagebot")
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(AUTHORIZATION, format!("Bearer {jwt}"))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(resp.json().await?)
+    }
+
+    /// Creates a JWT identifying the app, as described in
+    /// <https://docs.github.com/en/apps/creating-github-apps/authenticating-with-a-github-app/generating-a-json-web-token-jwt-for-a-github-app>.
+    fn jwt(&self, now: DateTime<Utc>) -> anyhow::Result<String> {
+        // Backdate the token a bit to allow for clock drift. GitHub rejects
+        // tokens which are valid for more than 10 minutes.
+        let claims = serde_json::json!({
+            "iat": (now - Duration::seconds(60)).timestamp(),
+            "exp": (now + Duration::minutes(9)).timestamp(),
+            "iss": self.app_id.to_string(),
+        });
+        let header = serde_json::json!({"alg": "RS256", "typ": "JWT"});
+        let message = format!(
+            "{}.{}",
+            base64_url(header.to_string().as_bytes()),
+            base64_url(claims.to_string().as_bytes())
+        );
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+        signer.update(message.as_bytes())?;
+        let signature = signer.sign_to_vec()?;
+        Ok(format!("{message}.{}", base64_url(&signature)))
+    }
+}
+
+/// Whether a request failed because the installation no longer exists or no
+/// longer grants access.
+fn is_gone(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .map_or(false, |status| {
+            status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::UNAUTHORIZED
+        })
+}
+
+/// Unpadded base64url encoding, as used by JWTs.
+fn base64_url(data: &[u8]) -> String {
+    openssl::base64::encode_block(data)
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::{rsa::Rsa, sign::Verifier};
+
+    #[test]
+    fn jwt() {
+        let rsa = Rsa::generate(2048).unwrap();
+        let pem = rsa.private_key_to_pem().unwrap();
+        let app = GithubApp::new(Client::new(), 1234, &pem).unwrap();
+        let now = Utc::now();
+        let jwt = app.jwt(now).unwrap();
+
+        let parts: Vec<_> = jwt.split('.').collect();
+        assert_eq!(parts.len(), 3);
+        assert!(parts
+            .iter()
+            .all(|part| !part.contains(['=', '+', '/'].as_slice())));
+
+        let decode = |part: &str| {
+            let mut part = part.replace('-', "+").replace('_', "/");
+            while part.len() % 4 != 0 {
+                part.push('=');
+            }
+            openssl::base64::decode_block(&part).unwrap()
+        };
+        let header: serde_json::Value = serde_json::from_slice(&decode(parts[0])).unwrap();
+        assert_eq!(header["alg"], "RS256");
+        let claims: serde_json::Value = serde_json::from_slice(&decode(parts[1])).unwrap();
+        assert_eq!(claims["iss"], "1234");
+        assert_eq!(claims["iat"], (now - Duration::seconds(60)).timestamp());
+        assert_eq!(claims["exp"], (now + Duration::minutes(9)).timestamp());
+
+        let public = PKey::public_key_from_pem(&rsa.public_key_to_pem().unwrap()).unwrap();
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &public).unwrap();
+        verifier
+            .update(format!("{}.{}", parts[0], parts[1]).as_bytes())
+            .unwrap();
+        assert!(verifier.verify(&decode(parts[2])).unwrap());
+    }
+
+    #[test]
+    fn base64_url_encoding() {
+        assert_eq!(base64_url(b""), "");
+        assert_eq!(base64_url(b"f"), "Zg");
+        assert_eq!(base64_url(&[0xfb, 0xff]), "-_8");
+    }
+}
@@ -0,0 +1,354 @@
+//! An in-memory cache of GitHub responses, revalidated with conditional
+//! requests.
+//!
+//! Successful GET responses with an `ETag` or `Last-Modified` header are kept,
+//! keyed by URL and `Accept` header, as the media type changes the body.
+//! Later requests for the same URL send `If-None-Match` (or
+//! `If-Modified-Since`), and if the resource hasn't changed GitHub responds
+//! with `304 Not Modified` and the cached body is used instead. Such responses
+//! don't count against the rate limit. A response is only reused for requests
+//! with the same values for the headers listed in its `Vary` header.
+//!
+//! The least recently used entries are evicted once the cached bodies take
+//! more than [`MAX_BYTES`].
+
+use bytes::Bytes;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED, VARY,
+};
+use reqwest::{Method, Request};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing as log;
+
+/// The maximum total size of the cached bodies.
+const MAX_BYTES: usize = 32 * 1024 * 1024;
+/// Larger responses are not cached.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+/// How many lookups to do between logging the hit rate.
+const LOG_STATS_EVERY: u64 = 100;
+
+pub(crate) struct ResponseCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, Entry>,
+    /// The total size of the cached bodies.
+    bytes: usize,
+    /// Incremented on every lookup and store, to track when entries were last
+    /// used.
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+struct Entry {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    /// The request's value of each header listed in the response's `Vary`
+    /// header.
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    body: Bytes,
+    last_used: u64,
+}
+
+/// Identifies a cacheable request; taken before the request is made
+/// conditional.
+pub(crate) struct CacheKey {
+    key: String,
+    headers: HeaderMap,
+}
+
+impl CacheKey {
+    pub(crate) fn new(req: &Request) -> CacheKey {
+        let accept = req
+            .headers()
+            .get(ACCEPT)
+            .map(|accept| String::from_utf8_lossy(accept.as_bytes()))
+            .unwrap_or_default();
+        CacheKey {
+            key: format!("{accept} {}", req.url()),
+            headers: req.headers().clone(),
+        }
+    }
+
+    /// Whether a response stored for a request with the same key also
+    /// applies to this one.
+    fn matches(&self, entry: &Entry) -> bool {
+        entry
+            .vary
+            .iter()
+            .all(|(name, value)| self.headers.get(name) == value.as_ref())
+    }
+}
+
+impl ResponseCache {
+    pub(crate) fn new() -> ResponseCache {
+        ResponseCache {
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Whether the response to the request may be cached.
+    ///
+    /// Requests which are already conditional are left alone.
+    pub(crate) fn is_cacheable(req: &Request) -> bool {
+        req.method() == Method::GET
+            && !req.headers().contains_key(IF_NONE_MATCH)
+            && !req.headers().contains_key(IF_MODIFIED_SINCE)
+    }
+
+    /// Makes a cacheable request conditional if its response is cached,
+    /// returning the cached body to use if the response is
+    /// `304 Not Modified`.
+    pub(crate) fn prepare(&self, key: &CacheKey, req: &mut Request) -> Option<Bytes> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let entry = inner
+            .entries
+            .get_mut(&key.key)
+            .filter(|entry| key.matches(entry))?;
+        entry.last_used = clock;
+        if let Some(etag) = &entry.etag {
+            req.headers_mut().insert(IF_NONE_MATCH, etag.clone());
+        } else if let Some(last_modified) = &entry.last_modified {
+            req.headers_mut()
+                .insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+        Some(entry.body.clone())
+    }
+
+    /// Records that a cached body was used.
+    pub(crate) fn hit(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.hits += 1;
+        inner.log_stats();
+    }
+
+    /// Records a successful response to a cacheable request, keeping it if it
+    /// can be revalidated later.
+    pub(crate) fn store(&self, key: &CacheKey, headers: &HeaderMap, body: &Bytes) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.misses += 1;
+        inner.log_stats();
+        inner.remove(&key.key);
+
+        let etag = headers.get(ETAG).cloned();
+        let last_modified = headers.get(LAST_MODIFIED).cloned();
+        let Some(vary) = vary(headers) else {
+            return;
+        };
+        if (etag.is_none() && last_modified.is_none()) || body.len() > MAX_BODY_SIZE {
+            return;
+        }
+        while inner.bytes + body.len() > MAX_BYTES {
+            inner.evict_least_recently_used();
+        }
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.bytes += body.len();
+        let vary = vary
+            .into_iter()
+            .map(|name| {
+                let value = key.headers.get(&name).cloned();
+                (name, value)
+            })
+            .collect();
+        inner.entries.insert(
+            key.key.clone(),
+            Entry {
+                etag,
+                last_modified,
+                vary,
+                body: body.clone(),
+                last_used,
+            },
+        );
+    }
+}
+
+/// The names of the headers listed in the `Vary` header of a response.
+///
+/// Returns `None` for `Vary: *` (or a malformed header), which means the
+/// response can't be reused.
+fn vary(headers: &HeaderMap) -> Option<Vec<HeaderName>> {
+    let mut names = Vec::new();
+    for value in headers.get_all(VARY) {
+        for name in value.to_str().ok()?.split(',') {
+            match name.trim() {
+                "" => {}
+                "*" => return None,
+                name => names.push(HeaderName::from_bytes(name.as_bytes()).ok()?),
+            }
+        }
+    }
+    Some(names)
+}
+
+impl Inner {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.body.len();
+        }
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.remove(&key);
+        }
+    }
+
+    fn log_stats(&self) {
+        let total = self.hits + self.misses;
+        if total % LOG_STATS_EVERY == 0 {
+            log::info!(
+                hits = self.hits,
+                misses = self.misses,
+                entries = self.entries.len(),
+                bytes = self.bytes,
+                "GitHub response cache hit rate: {:.1}%",
+                self.hits as f64 * 100.0 / total as f64
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::AUTHORIZATION;
+
+    fn get(url: &str) -> Request {
+        Request::new(Method::GET, url.parse().unwrap())
+    }
+
+    fn headers(etag: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_str(etag).unwrap());
+        headers
+    }
+
+    /// Looks up a request, like a client would before sending it.
+    fn lookup(cache: &ResponseCache, req: &mut Request) -> Option<Bytes> {
+        let key = CacheKey::new(req);
+        cache.prepare(&key, req)
+    }
+
+    #[test]
+    fn revalidates_with_etag() {
+        let cache = ResponseCache::new();
+        let url = "https://api.github.com/repos/rust-lang/rust";
+        let mut req = get(url);
+        assert!(ResponseCache::is_cacheable(&req));
+        assert_eq!(lookup(&cache, &mut req), None);
+        assert!(!req.headers().contains_key(IF_NONE_MATCH));
+
+        cache.store(
+            &CacheKey::new(&get(url)),
+            &headers("\"abc\""),
+            &Bytes::from_static(b"{}"),
+        );
+        let mut req = get(url);
+        assert_eq!(lookup(&cache, &mut req), Some(Bytes::from_static(b"{}")));
+        assert_eq!(req.headers()[IF_NONE_MATCH], "\"abc\"");
+        assert!(!ResponseCache::is_cacheable(&req));
+    }
+
+    #[test]
+    fn not_cached_without_validators() {
+        let cache = ResponseCache::new();
+        let url = "https://api.github.com/rate_limit";
+        cache.store(
+            &CacheKey::new(&get(url)),
+            &HeaderMap::new(),
+            &Bytes::from_static(b"{}"),
+        );
+        assert_eq!(lookup(&cache, &mut get(url)), None);
+    }
+
+    #[test]
+    fn keyed_by_accept() {
+        let cache = ResponseCache::new();
+        let url = "https://api.github.com/repos/rust-lang/rust/pulls/1";
+        let with_accept = |accept: &'static str| {
+            let mut req = get(url);
+            req.headers_mut()
+                .insert(ACCEPT, HeaderValue::from_static(accept));
+            req
+        };
+        let diff = with_accept("application/vnd.github.diff");
+        cache.store(
+            &CacheKey::new(&diff),
+            &headers("\"x\""),
+            &Bytes::from_static(b"diff"),
+        );
+        assert_eq!(lookup(&cache, &mut get(url)), None);
+        assert_eq!(
+            lookup(&cache, &mut with_accept("application/vnd.github+json")),
+            None
+        );
+        assert_eq!(
+            lookup(&cache, &mut with_accept("application/vnd.github.diff")),
+            Some(Bytes::from_static(b"diff"))
+        );
+    }
+
+    #[test]
+    fn honors_vary() {
+        let cache = ResponseCache::new();
+        let url = "https://api.github.com/repos/rust-lang/rust";
+        let with_token = |token: &'static str| {
+            let mut req = get(url);
+            req.headers_mut()
+                .insert(AUTHORIZATION, HeaderValue::from_static(token));
+            req
+        };
+        let mut response = headers("\"x\"");
+        response.insert(VARY, HeaderValue::from_static("Accept, Authorization"));
+        cache.store(
+            &CacheKey::new(&with_token("token a")),
+            &response,
+            &Bytes::new(),
+        );
+        assert!(lookup(&cache, &mut with_token("token a")).is_some());
+        assert!(lookup(&cache, &mut with_token("token b")).is_none());
+
+        response.insert(VARY, HeaderValue::from_static("*"));
+        cache.store(
+            &CacheKey::new(&with_token("token a")),
+            &response,
+            &Bytes::new(),
+        );
+        assert!(lookup(&cache, &mut with_token("token a")).is_none());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ResponseCache::new();
+        let url = |i| format!("https://api.github.com/{i}");
+        let store = |i| {
+            let body = Bytes::from(vec![0; MAX_BODY_SIZE]);
+            cache.store(&CacheKey::new(&get(&url(i))), &headers("\"x\""), &body);
+        };
+        let entries = MAX_BYTES / MAX_BODY_SIZE;
+        for i in 0..entries {
+            store(i);
+        }
+        // Use the first entry, so the second one is the oldest.
+        assert!(lookup(&cache, &mut get(&url(0))).is_some());
+        store(entries);
+        assert!(lookup(&cache, &mut get(&url(0))).is_some());
+        assert!(lookup(&cache, &mut get(&url(1))).is_none());
+        assert!(lookup(&cache, &mut get(&url(entries))).is_some());
+        assert_eq!(cache.inner.lock().unwrap().bytes, MAX_BYTES);
+    }
+}
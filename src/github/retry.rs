@@ -0,0 +1,286 @@
+//! When and how long to wait before retrying failed GitHub requests.
+//!
+//! Requests are retried when GitHub says it is rate limiting us (honoring
+//! `Retry-After`, or `X-RateLimit-Reset` once the limit is used up), and when
+//! it returns 502, 503 or 504, with jittered exponential backoff. The latter
+//! only applies to requests which are safe to repeat, since the first attempt
+//! may have been processed anyway.
+//!
+//! Rate limits which would make us wait longer than half of a handler's
+//! timeout fail right away instead, as the handler couldn't finish anyway.
+//!
+//! The number of attempts can be set with `GITHUB_MAX_ATTEMPTS`.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::future::Future;
+use std::time::Duration;
+use tracing as log;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+pub(crate) struct RetryPolicy {
+    /// The total number of attempts, including the first one.
+    max_attempts: u32,
+    /// The backoff before the second attempt, doubled for each further one.
+    base_delay: Duration,
+    max_delay: Duration,
+    /// The longest wait for a rate limit to be lifted.
+    max_rate_limit_wait: Duration,
+}
+
+/// Returns the policy configured by the environment.
+pub(crate) fn policy() -> &'static RetryPolicy {
+    static POLICY: Lazy<RetryPolicy> = Lazy::new(|| {
+        let max_attempts = match std::env::var("GITHUB_MAX_ATTEMPTS") {
+            Ok(v) => v.parse().unwrap_or_else(|_| {
+                log::warn!("ignoring invalid GITHUB_MAX_ATTEMPTS {v:?}");
+                DEFAULT_MAX_ATTEMPTS
+            }),
+            Err(_) => DEFAULT_MAX_ATTEMPTS,
+        };
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            ..RetryPolicy::default()
+        }
+    });
+    &POLICY
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            max_rate_limit_wait: crate::handlers::HANDLER_TIMEOUT / 2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns how long to wait before retrying a request which got the given
+    /// response, or `None` if it should not be retried.
+    ///
+    /// `attempt` is the number of attempts made so far, and `idempotent`
+    /// whether the request may be repeated if GitHub may have processed it.
+    pub(crate) fn delay(
+        &self,
+        attempt: u32,
+        idempotent: bool,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        match status {
+            StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS => {
+                let delay = if let Some(delay) = retry_after(headers, Utc::now()) {
+                    delay
+                } else if header(headers, "X-RateLimit-Remaining") == Some("0") {
+                    let reset = header(headers, "X-RateLimit-Reset")?.parse::<i64>().ok()?;
+                    let wait = (reset - Utc::now().timestamp()).max(0) as u64;
+                    // Leave some leeway for clock differences.
+                    Duration::from_secs(wait + 10)
+                } else {
+                    // Otherwise it is a permission problem.
+                    return None;
+                };
+                if delay > self.max_rate_limit_wait {
+                    log::warn!("not waiting {delay:?} for the rate limit to be lifted");
+                    return None;
+                }
+                Some(delay)
+            }
+            StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+                if idempotent =>
+            {
+                Some(self.backoff(attempt))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns how long to wait before retrying after a rate limit which
+    /// didn't say how long to wait, such as `RATE_LIMITED` GraphQL errors, or
+    /// `None` if there are no attempts left.
+    pub(crate) fn rate_limited_delay(&self, attempt: u32) -> Option<Duration> {
+        (attempt < self.max_attempts).then(|| self.backoff(attempt))
+    }
+
+    /// Exponential backoff with jitter, so concurrent requests don't all
+    /// retry at the same time.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        exp.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok()
+}
+
+/// Parses `Retry-After`, which is either a number of seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = header(headers, RETRY_AFTER.as_str())?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Runs an octocrab request, retrying it according to the policy.
+///
+/// Octocrab doesn't expose the response headers, so rate limits are retried
+/// with backoff. Pass `idempotent: false` for requests which must not be
+/// repeated after a server error, such as creating something.
+pub(crate) async fn octocrab<T, F, Fut>(idempotent: bool, mut f: F) -> octocrab::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = octocrab::Result<T>>,
+{
+    let policy = policy();
+    let mut attempt = 1;
+    loop {
+        let err = match f().await {
+            Ok(v) => return Ok(v),
+            Err(e) => e,
+        };
+        let delay = match &err {
+            octocrab::Error::GitHub { source, .. } => {
+                let rate_limited = matches!(
+                    source.status_code,
+                    StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+                ) && source.message.to_lowercase().contains("rate limit");
+                if rate_limited {
+                    policy.rate_limited_delay(attempt)
+                } else {
+                    policy.delay(attempt, idempotent, source.status_code, &HeaderMap::new())
+                }
+            }
+            _ => None,
+        };
+        let Some(delay) = delay else {
+            return Err(err);
+        };
+        log::warn!("retrying octocrab request in {delay:?} after error: {err}");
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        let policy = RetryPolicy::default();
+        let h = headers(&[("retry-after", "42")]);
+        assert_eq!(
+            policy.delay(1, false, StatusCode::FORBIDDEN, &h),
+            Some(Duration::from_secs(42))
+        );
+        // No attempts left.
+        assert_eq!(policy.delay(3, false, StatusCode::FORBIDDEN, &h), None);
+    }
+
+    #[test]
+    fn long_rate_limits_fail() {
+        let policy = RetryPolicy::default();
+        let h = headers(&[("retry-after", "3600")]);
+        assert_eq!(policy.delay(1, true, StatusCode::FORBIDDEN, &h), None);
+        let reset = (Utc::now().timestamp() + 3600).to_string();
+        let h = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", &reset),
+        ]);
+        assert_eq!(policy.delay(1, true, StatusCode::FORBIDDEN, &h), None);
+    }
+
+    #[test]
+    fn retry_after_date() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let h = headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:30 GMT")]);
+        assert_eq!(retry_after(&h, now), Some(Duration::from_secs(30)));
+        let h = headers(&[("retry-after", "Wed, 21 Oct 2015 07:27:00 GMT")]);
+        assert_eq!(retry_after(&h, now), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn malformed_headers() {
+        let policy = RetryPolicy::default();
+        let h = headers(&[
+            ("retry-after", "soon"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "tomorrow"),
+        ]);
+        assert_eq!(policy.delay(1, true, StatusCode::FORBIDDEN, &h), None);
+    }
+
+    #[test]
+    fn rate_limit_reset() {
+        let policy = RetryPolicy::default();
+        let reset = (Utc::now().timestamp() + 60).to_string();
+        let h = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", &reset),
+        ]);
+        let delay = policy.delay(1, false, StatusCode::FORBIDDEN, &h).unwrap();
+        assert!(delay > Duration::from_secs(60) && delay <= Duration::from_secs(70));
+
+        // A 403 with requests remaining is not a rate limit.
+        let h = headers(&[
+            ("x-ratelimit-remaining", "10"),
+            ("x-ratelimit-reset", &reset),
+        ]);
+        assert_eq!(policy.delay(1, true, StatusCode::FORBIDDEN, &h), None);
+    }
+
+    #[test]
+    fn server_errors() {
+        let policy = RetryPolicy::default();
+        let none = HeaderMap::new();
+        for attempt in 1..3 {
+            let delay = policy
+                .delay(attempt, true, StatusCode::BAD_GATEWAY, &none)
+                .unwrap();
+            let max = Duration::from_secs(1 << (attempt - 1));
+            assert!(delay >= max / 2 && delay <= max, "{delay:?}");
+        }
+        assert_eq!(
+            policy.delay(1, false, StatusCode::SERVICE_UNAVAILABLE, &none),
+            None
+        );
+        assert_eq!(
+            policy.delay(1, true, StatusCode::INTERNAL_SERVER_ERROR, &none),
+            None
+        );
+        assert_eq!(policy.delay(1, true, StatusCode::NOT_FOUND, &none), None);
+    }
+}
@@ -1,10 +1,16 @@
-use crate::config::{self, Config, ConfigurationError};
+use crate::config::{Config, ConfigurationError, HandlerConfig};
 use crate::github::{Event, GithubClient, IssueCommentAction, IssuesAction, IssuesEvent};
+use async_trait::async_trait;
+use futures::future::{join_all, BoxFuture, FutureExt};
 use octocrab::Octocrab;
-use parser::command::{assign::AssignCommand, Command, Input};
+use parser::command::{assign::AssignCommand, Command, CommandKind, Input};
 use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing as log;
+use tracing::Instrument;
 
 #[derive(Debug)]
 pub enum HandlerError {
@@ -29,6 +35,7 @@ mod close;
 pub mod docs_update;
 mod github_releases;
 mod glacier;
+mod help;
 pub mod jobs;
 mod major_change;
 mod mentions;
@@ -46,110 +53,281 @@ mod review_submitted;
 mod rfc_helper;
 pub mod rustc_commits;
 mod shortcut;
+mod validate_config;
 
-pub async fn handle(ctx: &Context, event: &Event) -> Vec<HandlerError> {
-    let config = config::get(&ctx.github, event.repo()).await;
-    if let Err(e) = &config {
-        log::warn!("configuration error {}: {e}", event.repo().full_name);
-    }
-    let mut errors = Vec::new();
+/// What happened when the handlers ran for an event.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub errors: Vec<HandlerError>,
+    /// The handlers which failed with an internal error, and should run again
+    /// if the event is retried.
+    pub failed: Vec<&'static str>,
+}
 
-    if let (Ok(config), Event::Issue(event)) = (config.as_ref(), event) {
-        handle_issue(ctx, event, config, &mut errors).await;
+impl Outcome {
+    fn fail(&mut self, name: &'static str, err: anyhow::Error) {
+        crate::metrics::handler_error(name);
+        self.errors.push(HandlerError::Other(err));
+        if !self.failed.contains(&name) {
+            self.failed.push(name);
+        }
     }
 
-    if let Some(body) = event.comment_body() {
-        handle_command(ctx, event, &config, body, &mut errors).await;
+    fn merge(&mut self, other: Outcome) {
+        self.errors.extend(other.errors);
+        for name in other.failed {
+            if !self.failed.contains(&name) {
+                self.failed.push(name);
+            }
+        }
     }
+}
 
-    if let Err(e) = notification::handle(ctx, event).await {
-        log::error!(
-            "failed to process event {:?} with notification handler: {:?}",
-            event,
-            e
-        );
-    }
+/// Whether the handler called `name` is among those selected to run; `None`
+/// selects all handlers.
+fn selected(only: Option<&[String]>, name: &str) -> bool {
+    only.map_or(true, |only| only.iter().any(|n| n == name))
+}
 
-    if let Err(e) = rustc_commits::handle(ctx, event).await {
-        log::error!(
-            "failed to process event {:?} with rustc_commits handler: {:?}",
-            event,
-            e
-        );
-    }
+/// Runs the handlers for an event, given the configuration of its repository.
+///
+/// If `only` is given, just the handlers with these names run: those which
+/// failed when the event was processed before.
+pub async fn handle(
+    ctx: &Context,
+    event: &Event,
+    config: &Result<Arc<Config>, ConfigurationError>,
+    only: Option<&[String]>,
+) -> Outcome {
+    // Handlers which change the issue run one after the other, as their order
+    // matters: e.g. `@rustbot label` and `@rustbot ready` in one comment, or
+    // labels changed by a review and by commands in its body.
+    let issue_handlers = async {
+        let mut outcome = Outcome::default();
 
-    if let Err(e) = milestone_prs::handle(ctx, event).await {
-        log::error!(
-            "failed to process event {:?} with milestone_prs handler: {:?}",
-            event,
-            e
-        );
-    }
+        if let (Ok(config), Event::Issue(event)) = (config.as_ref(), event) {
+            handle_issue(ctx, event, config, only, &mut outcome).await;
+        }
 
-    if let Err(e) = rfc_helper::handle(ctx, event).await {
-        log::error!(
-            "failed to process event {:?} with rfc_helper handler: {:?}",
-            event,
-            e
-        );
-    }
+        if let Some(body) = event.comment_body() {
+            handle_command(ctx, event, config, body, only, &mut outcome).await;
+        }
 
-    if let Some(config) = config
-        .as_ref()
-        .ok()
-        .and_then(|c| c.review_submitted.as_ref())
-    {
-        if let Err(e) = review_submitted::handle(ctx, event, config).await {
-            log::error!(
-                "failed to process event {:?} with review_submitted handler: {:?}",
-                event,
-                e
-            )
+        if let Some(config) = config
+            .as_ref()
+            .ok()
+            .filter(|_| selected(only, "review_submitted"))
+            .and_then(|c| c.review_submitted.as_ref())
+        {
+            let handler = review_submitted::handle(ctx, event, config);
+            if let Err(e) = run_handler("review_submitted", handler).await {
+                log_error("review_submitted", event, e);
+            }
         }
-    }
 
+        outcome
+    };
+
+    // Nobody would notice a pull request which wasn't validated, so this
+    // handler runs again when the event is retried.
+    let validation = async {
+        let mut outcome = Outcome::default();
+        if selected(only, "validate_config") {
+            run_handler("validate_config", validate_config::handle(ctx, event))
+                .await
+                .unwrap_or_else(|err| outcome.fail("validate_config", err));
+        }
+        outcome
+    };
+
+    // The other handlers are independent, and run concurrently. Their errors
+    // are only logged, so they don't run again when the event is retried.
+    let mut handlers: Vec<(&str, BoxFuture<'_, anyhow::Result<()>>)> = vec![
+        ("notification", notification::handle(ctx, event).boxed()),
+        ("rustc_commits", rustc_commits::handle(ctx, event).boxed()),
+        ("milestone_prs", milestone_prs::handle(ctx, event).boxed()),
+        ("rfc_helper", rfc_helper::handle(ctx, event).boxed()),
+    ];
     if let Some(ghr_config) = config
         .as_ref()
         .ok()
         .and_then(|c| c.github_releases.as_ref())
     {
-        if let Err(e) = github_releases::handle(ctx, event, ghr_config).await {
-            log::error!(
-                "failed to process event {:?} with github_releases handler: {:?}",
-                event,
-                e
-            );
+        handlers.push((
+            "github_releases",
+            github_releases::handle(ctx, event, ghr_config).boxed(),
+        ));
+    }
+    handlers.retain(|(name, _)| selected(only, name));
+    let other_handlers = join_all(handlers.into_iter().map(|(name, handler)| async move {
+        if let Err(e) = run_handler(name, handler).await {
+            log_error(name, event, e);
         }
+    }));
+
+    let (mut outcome, validated, _) = futures::join!(issue_handlers, validation, other_handlers);
+    outcome.merge(validated);
+    outcome
+}
+
+/// How long a handler may take for one event before it is abandoned.
+pub(crate) const HANDLER_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Runs a handler in its own tracing span, failing if it panics or takes
+/// longer than [`HANDLER_TIMEOUT`].
+async fn run_handler<T>(
+    name: &str,
+    handler: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let span = tracing::info_span!("handler", handler = name);
+    match tokio::time::timeout(HANDLER_TIMEOUT, catch_panic(name, handler))
+        .instrument(span)
+        .await
+    {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!(
+            "the {name} handler timed out after {HANDLER_TIMEOUT:?}"
+        )),
     }
+}
 
-    errors
+/// Turns a panic in a handler into an error, so that the other handlers
+/// still run.
+///
+/// The error is logged and counted by the caller like any other, so a panic
+/// shows up in both the handler error and panic metrics.
+pub(crate) async fn catch_panic<T>(
+    name: &str,
+    handler: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    match AssertUnwindSafe(handler).catch_unwind().await {
+        Ok(result) => result,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| panic.downcast_ref::<String>().map(|s| s.as_str()))
+                .unwrap_or("unknown cause");
+            crate::metrics::handler_panic(name);
+            Err(anyhow::anyhow!("the {name} handler panicked: {message}"))
+        }
+    }
 }
 
-macro_rules! issue_handlers {
-    ($($name:ident,)*) => {
-        async fn handle_issue(
-            ctx: &Context,
-            event: &IssuesEvent,
-            config: &Arc<Config>,
-            errors: &mut Vec<HandlerError>,
-        ) {
-            $(
-            match $name::parse_input(ctx, event, config.$name.as_ref()).await {
-                Err(err) => errors.push(HandlerError::Message(err)),
-                Ok(Some(input)) => {
-                    if let Some(config) = &config.$name {
-                        $name::handle_input(ctx, config, event, input).await.unwrap_or_else(|err| errors.push(HandlerError::Other(err)));
-                    } else {
-                        errors.push(HandlerError::Message(format!(
-                            "The feature `{}` is not enabled in this repository.\n\
-                            To enable it add its section in the `triagebot.toml` \
-                            in the root of the repository.",
-                            stringify!($name)
-                        )));
-                    }
-                }
-                Ok(None) => {}
-            })*
+fn log_error(name: &str, event: &Event, e: anyhow::Error) {
+    crate::metrics::handler_error(name);
+    log::error!(
+        "failed to process event {:?} with {} handler: {:?}",
+        event,
+        name,
+        e
+    );
+}
+
+/// A handler for events on issues and pull requests (e.g. label changes).
+///
+/// Handlers are registered in [`ISSUE_HANDLERS`].
+#[async_trait]
+pub(crate) trait IssueHandler: Sync {
+    /// The handler's table in `triagebot.toml`.
+    type Config: HandlerConfig;
+    /// What the handler found to do in an event.
+    type Input: Send;
+
+    /// The name of the handler, in metrics and messages.
+    const NAME: &'static str;
+
+    /// Checks whether the handler has something to do for the event.
+    ///
+    /// This is also called when the handler is not enabled, in which case
+    /// returning some input tells the user to enable it. Errors are posted as
+    /// a comment.
+    async fn parse_input(
+        &self,
+        ctx: &Context,
+        event: &IssuesEvent,
+        config: Option<&Self::Config>,
+    ) -> Result<Option<Self::Input>, String>;
+
+    async fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &Self::Config,
+        event: &IssuesEvent,
+        input: Self::Input,
+    ) -> anyhow::Result<()>;
+}
+
+/// A handler for commands in comments and issue descriptions, which are
+/// parsed by the `parser` crate.
+///
+/// Handlers are registered in [`COMMAND_HANDLERS`].
+#[async_trait]
+pub(crate) trait CommandHandler: Sync {
+    /// The handler's table in `triagebot.toml`.
+    type Config: HandlerConfig;
+    /// The command, as parsed by the `parser` crate.
+    type Command: CommandKind + Send;
+
+    /// The name of the handler, in metrics and messages.
+    ///
+    /// Retries select handlers by name, so it must differ from the names of
+    /// the [`IssueHandler`]s: the handler of a feature which also has one is
+    /// named `<feature>_command`.
+    const NAME: &'static str;
+
+    /// Describes the commands, for `@rustbot help`.
+    fn usage(config: &Self::Config, bot: &str) -> Vec<Usage>;
+
+    /// Who may use the command.
+    fn permission(command: &Self::Command) -> Permission;
+
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        config: &Self::Config,
+        event: &Event,
+        command: Self::Command,
+    ) -> anyhow::Result<()>;
+}
+
+/// Who may use a command.
+///
+/// Commands limited to team members are rejected before they reach the
+/// handler.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Permission {
+    Anyone,
+    TeamMembers {
+        /// Whether to ignore others without telling them.
+        silent: bool,
+        /// What others are told.
+        message: &'static str,
+    },
+    /// Explained in the description of the command, and checked by the
+    /// handler.
+    Described,
+}
+
+/// A command, as listed by `@rustbot help`.
+#[derive(Debug)]
+pub(crate) struct Usage {
+    /// How to write the command, e.g. `@rustbot close`.
+    pub(crate) syntax: String,
+    pub(crate) description: String,
+    pub(crate) permission: Permission,
+}
+
+impl Usage {
+    pub(crate) fn new(
+        syntax: impl Into<String>,
+        description: impl Into<String>,
+        permission: Permission,
+    ) -> Usage {
+        Usage {
+            syntax: syntax.into(),
+            description: description.into(),
+            permission,
         }
     }
 }
@@ -157,126 +335,322 @@ macro_rules! issue_handlers {
 // Handle events that happened on issues
 //
 // This is for events that happen only on issues (e.g. label changes).
-// Each module in the list must contain the functions `parse_input` and `handle_input`.
-issue_handlers! {
-    assign,
-    autolabel,
-    major_change,
-    mentions,
-    no_merges,
-    notify_zulip,
-    review_requested,
+static ISSUE_HANDLERS: &[&dyn RegisteredIssueHandler] = &[
+    &assign::Handler,
+    &autolabel::Handler,
+    &major_change::Handler,
+    &mentions::Handler,
+    &no_merges::Handler,
+    &notify_zulip::Handler,
+    &review_requested::Handler,
+];
+
+// Handle commands in comments/issues body
+//
+// Each variant of `parser::command::Command` must be taken by one of these,
+// except for `Unknown` and `Ambiguous`. `@rustbot help` lists the commands in
+// this order.
+static COMMAND_HANDLERS: &[&dyn RegisteredCommandHandler] = &[
+    &relabel::Handler,
+    &assign::Handler,
+    &ping::Handler,
+    &nominate::Handler,
+    &prioritize::Handler,
+    &major_change::Handler,
+    &glacier::Handler,
+    &shortcut::Handler,
+    &note::Handler,
+    &close::Handler,
+    &help::Handler,
+];
+
+/// Reports a command which couldn't be parsed, linking to the comment if
+/// there is one.
+fn parse_failed(what: &str, event: &Event, err: impl fmt::Display) -> HandlerError {
+    HandlerError::Message(match event.html_url() {
+        Some(url) => format!("Parsing {what} in [comment]({url}) failed: {err}"),
+        None => format!("Parsing {what} failed: {err}"),
+    })
 }
 
-macro_rules! command_handlers {
-    ($($name:ident: $enum:ident,)*) => {
-        async fn handle_command(
-            ctx: &Context,
-            event: &Event,
-            config: &Result<Arc<Config>, ConfigurationError>,
-            body: &str,
-            errors: &mut Vec<HandlerError>,
-        ) {
-            match event {
-                Event::Issue(e) => if !matches!(e.action, IssuesAction::Opened | IssuesAction::Edited) {
-                    // no change in issue's body for these events, so skip
-                    log::debug!("skipping event, issue was {:?}", e.action);
-                    return;
-                }
-                Event::IssueComment(e) => if e.action == IssueCommentAction::Deleted {
-                    // don't execute commands again when comment is deleted
-                    log::debug!("skipping event, comment was {:?}", e.action);
-                    return;
-                }
-                Event::Push(_) | Event::Create(_) => {
-                    log::debug!("skipping unsupported event");
-                    return;
+/// The feature a handler belongs to, for messages.
+fn feature(name: &str) -> &str {
+    name.strip_suffix("_command").unwrap_or(name)
+}
+
+fn not_enabled(name: &str, config_key: &str) -> HandlerError {
+    HandlerError::Message(format!(
+        "The feature `{name}` is not enabled in this repository.\n\
+        To enable it add its `[{config_key}]` section in the `triagebot.toml` \
+        in the root of the repository."
+    ))
+}
+
+/// An [`IssueHandler`] with its types erased, so that handlers can be listed
+/// together.
+#[async_trait]
+trait RegisteredIssueHandler: Sync {
+    fn name(&self) -> &'static str;
+
+    async fn handle(
+        &self,
+        ctx: &Context,
+        event: &IssuesEvent,
+        config: &Config,
+        outcome: &mut Outcome,
+    );
+}
+
+#[async_trait]
+impl<H: IssueHandler> RegisteredIssueHandler for H {
+    fn name(&self) -> &'static str {
+        H::NAME
+    }
+
+    async fn handle(
+        &self,
+        ctx: &Context,
+        event: &IssuesEvent,
+        config: &Config,
+        outcome: &mut Outcome,
+    ) {
+        let input = self.parse_input(ctx, event, H::Config::get(config));
+        match run_handler(H::NAME, input.map(Ok)).await {
+            Err(err) => outcome.fail(H::NAME, err),
+            Ok(Err(err)) => {
+                crate::metrics::handler_error(H::NAME);
+                outcome.errors.push(HandlerError::Message(err));
+            }
+            Ok(Ok(Some(input))) => {
+                if let Some(config) = H::Config::get(config) {
+                    run_handler(H::NAME, self.handle_input(ctx, config, event, input))
+                        .await
+                        .unwrap_or_else(|err| outcome.fail(H::NAME, err));
+                } else {
+                    outcome.errors.push(not_enabled(H::NAME, H::Config::KEY));
                 }
             }
+            Ok(Ok(None)) => {}
+        }
+    }
+}
 
-            let input = Input::new(&body, vec![&ctx.username, "triagebot"]);
-            let commands = if let Some(previous) = event.comment_from() {
-                let prev_commands = Input::new(&previous, vec![&ctx.username, "triagebot"]).collect::<Vec<_>>();
-                input.filter(|cmd| !prev_commands.contains(cmd)).collect::<Vec<_>>()
-            } else {
-                input.collect()
-            };
+/// A [`CommandHandler`] with its types erased, so that handlers can be listed
+/// together.
+#[async_trait]
+trait RegisteredCommandHandler: Sync {
+    fn name(&self) -> &'static str;
 
-            log::info!("Comment parsed to {:?}", commands);
+    /// The commands which are enabled by `config`.
+    fn usage(&self, config: &Config, bot: &str) -> Vec<Usage>;
 
-            if commands.is_empty() {
-                return;
-            }
+    /// Handles the command, or gives it back if it's another handler's.
+    async fn handle<'a>(
+        &self,
+        ctx: &Context,
+        config: &Config,
+        event: &Event,
+        command: Command<'a>,
+        only: Option<&[String]>,
+        outcome: &mut Outcome,
+    ) -> Result<(), Command<'a>>;
+}
 
-            let config = match config {
-                Ok(config) => config,
-                Err(e @ ConfigurationError::Missing) => {
-                    // r? is conventionally used to mean "hey, can you review"
-                    // even if the repo doesn't have a triagebot.toml. In that
-                    // case, just ignore it.
-                    if commands
-                        .iter()
-                        .all(|cmd| matches!(cmd, Command::Assign(Ok(AssignCommand::ReviewName { .. }))))
-                    {
-                        return;
-                    }
-                    return errors.push(HandlerError::Message(e.to_string()));
-                }
-                Err(e @ ConfigurationError::Toml(_)) => {
-                    return errors.push(HandlerError::Message(e.to_string()));
-                }
-                Err(e @ ConfigurationError::Http(_)) => {
-                    return errors.push(HandlerError::Other(e.clone().into()));
-                }
-            };
-
-            for command in commands {
-                match command {
-                    $(
-                    Command::$enum(Ok(command)) => {
-                        if let Some(config) = &config.$name {
-                            $name::handle_command(ctx, config, event, command)
-                                .await
-                                .unwrap_or_else(|err| errors.push(HandlerError::Other(err)));
-                        } else {
-                            errors.push(HandlerError::Message(format!(
-                                "The feature `{}` is not enabled in this repository.\n\
-                                To enable it add its section in the `triagebot.toml` \
-                                in the root of the repository.",
-                                stringify!($name)
-                            )));
+#[async_trait]
+impl<H: CommandHandler> RegisteredCommandHandler for H {
+    fn name(&self) -> &'static str {
+        H::NAME
+    }
+
+    fn usage(&self, config: &Config, bot: &str) -> Vec<Usage> {
+        H::Config::get(config)
+            .map(|config| H::usage(config, bot))
+            .unwrap_or_default()
+    }
+
+    async fn handle<'a>(
+        &self,
+        ctx: &Context,
+        config: &Config,
+        event: &Event,
+        command: Command<'a>,
+        only: Option<&[String]>,
+        outcome: &mut Outcome,
+    ) -> Result<(), Command<'a>> {
+        let command = H::Command::take(command)?;
+        if !selected(only, H::NAME) {
+            return Ok(());
+        }
+        match command {
+            Ok(command) => {
+                let Some(config) = H::Config::get(config) else {
+                    outcome
+                        .errors
+                        .push(not_enabled(feature(H::NAME), H::Config::KEY));
+                    return Ok(());
+                };
+                if let Permission::TeamMembers { silent, message } = H::permission(&command) {
+                    match event.user().is_team_member(&ctx.github).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            if !silent {
+                                outcome
+                                    .errors
+                                    .push(HandlerError::Message(message.to_string()));
+                            }
+                            return Ok(());
+                        }
+                        Err(err) => {
+                            outcome.fail(H::NAME, err.context("failed to check team membership"));
+                            return Ok(());
                         }
                     }
-                    Command::$enum(Err(err)) => {
-                        errors.push(HandlerError::Message(format!(
-                            "Parsing {} command in [comment]({}) failed: {}",
-                            stringify!($name),
-                            event.html_url().expect("has html url"),
-                            err
-                        )));
-                    })*
                 }
+                run_handler(H::NAME, self.handle_command(ctx, config, event, command))
+                    .await
+                    .unwrap_or_else(|err| outcome.fail(H::NAME, err));
+            }
+            Err(err) => {
+                let what = format!("{} command", feature(H::NAME));
+                outcome.errors.push(parse_failed(&what, event, err));
             }
         }
+        Ok(())
     }
 }
 
-// Handle commands in comments/issues body
-//
-// This is for handlers for commands parsed by the `parser` crate.
-// Each variant of `parser::command::Command` must be in this list,
-// preceded by the module containing the coresponding `handle_command` function
-command_handlers! {
-    assign: Assign,
-    glacier: Glacier,
-    nominate: Nominate,
-    ping: Ping,
-    prioritize: Prioritize,
-    relabel: Relabel,
-    major_change: Second,
-    shortcut: Shortcut,
-    close: Close,
-    note: Note,
+/// The commands enabled by `config`, for `@rustbot help`.
+fn usage(config: &Config, bot: &str) -> Vec<Usage> {
+    COMMAND_HANDLERS
+        .iter()
+        .flat_map(|handler| handler.usage(config, bot))
+        .collect()
+}
+
+async fn handle_issue(
+    ctx: &Context,
+    event: &IssuesEvent,
+    config: &Arc<Config>,
+    only: Option<&[String]>,
+    outcome: &mut Outcome,
+) {
+    for handler in ISSUE_HANDLERS {
+        if selected(only, handler.name()) {
+            handler.handle(ctx, event, config, outcome).await;
+        }
+    }
+}
+
+async fn handle_command(
+    ctx: &Context,
+    event: &Event,
+    config: &Result<Arc<Config>, ConfigurationError>,
+    body: &str,
+    only: Option<&[String]>,
+    outcome: &mut Outcome,
+) {
+    match event {
+        Event::Issue(e) => {
+            if !matches!(e.action, IssuesAction::Opened | IssuesAction::Edited) {
+                // no change in issue's body for these events, so skip
+                log::debug!("skipping event, issue was {:?}", e.action);
+                return;
+            }
+        }
+        Event::IssueComment(e) => {
+            if e.action == IssueCommentAction::Deleted {
+                // don't execute commands again when comment is deleted
+                log::debug!("skipping event, comment was {:?}", e.action);
+                return;
+            }
+        }
+        Event::Push(_) | Event::Create(_) => {
+            log::debug!("skipping unsupported event");
+            return;
+        }
+    }
+
+    let input = Input::new(&body, vec![&ctx.username, "triagebot"]);
+    let commands = if let Some(previous) = event.comment_from() {
+        let prev_commands =
+            Input::new(&previous, vec![&ctx.username, "triagebot"]).collect::<Vec<_>>();
+        input
+            .filter(|cmd| !prev_commands.contains(cmd))
+            .collect::<Vec<_>>()
+    } else {
+        input.collect()
+    };
+
+    log::info!("Comment parsed to {:?}", commands);
+
+    if commands.is_empty() {
+        return;
+    }
+
+    let config = match config {
+        Ok(config) => config,
+        Err(e @ ConfigurationError::Missing) => {
+            // r? is conventionally used to mean "hey, can you review"
+            // even if the repo doesn't have a triagebot.toml. In that
+            // case, just ignore it.
+            // The same goes for misspelled commands, which may not be
+            // commands at all.
+            if commands.iter().all(|cmd| {
+                matches!(
+                    cmd,
+                    Command::Assign(Ok(AssignCommand::ReviewName { .. })) | Command::Unknown(_)
+                )
+            }) {
+                return;
+            }
+            return outcome.errors.push(HandlerError::Message(e.to_string()));
+        }
+        Err(e @ (ConfigurationError::Toml(_) | ConfigurationError::OrgToml(_))) => {
+            return outcome.errors.push(HandlerError::Message(e.to_string()));
+        }
+        Err(e @ ConfigurationError::Http(_)) => {
+            return outcome.errors.push(HandlerError::Other(e.clone().into()));
+        }
+    };
+
+    // Only the first misspelled command gets a suggestion, to keep the
+    // reply short.
+    let mut suggested = !config.command_suggestions;
+    'commands: for mut command in commands {
+        for handler in COMMAND_HANDLERS {
+            match handler
+                .handle(ctx, config, event, command, only, outcome)
+                .await
+            {
+                Ok(()) => continue 'commands,
+                Err(unhandled) => command = unhandled,
+            }
+        }
+        // Errors which don't belong to a handler were reported the first
+        // time.
+        if only.is_some() {
+            continue;
+        }
+        match command {
+            Command::Unknown(Ok(command)) => {
+                if !suggested {
+                    suggested = true;
+                    outcome.errors.push(HandlerError::Message(format!(
+                        "Unknown command `{}`. Did you mean `@{} {}`? \
+                        See `@{} help` for the available commands.",
+                        command.word, ctx.username, command.suggestion, ctx.username
+                    )));
+                }
+            }
+            Command::Unknown(Err(_)) => {}
+            Command::Ambiguous(err) => {
+                outcome.errors.push(parse_failed("command", event, err));
+            }
+            command => {
+                log::error!("no handler registered for command {:?}", command);
+            }
+        }
+    }
 }
 
 pub struct Context {
@@ -285,3 +659,70 @@ pub struct Context {
     pub username: String,
     pub octocrab: Octocrab,
 }
+
+impl Context {
+    /// Returns a copy of this context whose GitHub client is in dry-run mode.
+    pub fn dry_run(&self) -> Context {
+        Context {
+            github: self.github.clone().dry_run(),
+            db: self.db.clone(),
+            username: self.username.clone(),
+            octocrab: self.octocrab.clone(),
+        }
+    }
+
+    /// Returns a context authenticated as the GitHub App installation for
+    /// the given repository (`owner/name`).
+    ///
+    /// Returns `None` if triagebot is not running as a GitHub App, in which
+    /// case this context should be used as is.
+    pub async fn for_repository(&self, repo: &str) -> anyhow::Result<Option<Context>> {
+        let Some(token) = self.github.installation_token(repo).await? else {
+            return Ok(None);
+        };
+        let github = self.github.with_token(token);
+        Ok(Some(Context {
+            octocrab: github.octocrab()?,
+            github,
+            db: self.db.clone(),
+            username: self.username.clone(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handler_names_are_unique() {
+        let mut names: Vec<_> = ISSUE_HANDLERS
+            .iter()
+            .map(|handler| handler.name())
+            .chain(COMMAND_HANDLERS.iter().map(|handler| handler.name()))
+            .chain(["review_submitted", "validate_config"])
+            .collect();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count, "{names:?}");
+    }
+
+    #[tokio::test]
+    async fn panics_are_errors() {
+        let result = catch_panic("test", async {
+            if true {
+                panic!("oops");
+            }
+            Ok(())
+        })
+        .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "the test handler panicked: oops"
+        );
+
+        let result = catch_panic("test", async { Ok(1) }).await;
+        assert_eq!(result.unwrap(), 1);
+    }
+}
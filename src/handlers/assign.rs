@@ -20,10 +20,13 @@
 use crate::{
     config::AssignConfig,
     github::{self, Event, Issue, IssuesAction, Selection},
-    handlers::{Context, GithubClient, IssuesEvent},
+    handlers::{
+        CommandHandler, Context, GithubClient, IssueHandler, IssuesEvent, Permission, Usage,
+    },
     interactions::EditIssueBody,
 };
 use anyhow::{bail, Context as _};
+use async_trait::async_trait;
 use parser::command::assign::AssignCommand;
 use parser::command::{Command, Input};
 use rand::seq::IteratorRandom;
@@ -84,112 +87,124 @@ pub(super) struct AssignInput {
     git_diff: String,
 }
 
-/// Prepares the input when a new PR is opened.
-pub(super) async fn parse_input(
-    ctx: &Context,
-    event: &IssuesEvent,
-    config: Option<&AssignConfig>,
-) -> Result<Option<AssignInput>, String> {
-    let config = match config {
-        Some(config) => config,
-        None => return Ok(None),
-    };
-    if config.owners.is_empty()
-        || !matches!(event.action, IssuesAction::Opened)
-        || !event.issue.is_pr()
-    {
-        return Ok(None);
-    }
-    let git_diff = match event.issue.diff(&ctx.github).await {
-        Ok(None) => return Ok(None),
-        Err(e) => {
-            log::error!("failed to fetch diff: {:?}", e);
+pub(super) struct Handler;
+
+#[async_trait]
+impl IssueHandler for Handler {
+    type Config = AssignConfig;
+    type Input = AssignInput;
+
+    const NAME: &'static str = "assign";
+
+    /// Prepares the input when a new PR is opened.
+    async fn parse_input(
+        &self,
+        ctx: &Context,
+        event: &IssuesEvent,
+        config: Option<&AssignConfig>,
+    ) -> Result<Option<AssignInput>, String> {
+        let config = match config {
+            Some(config) => config,
+            None => return Ok(None),
+        };
+        if config.owners.is_empty()
+            || !matches!(event.action, IssuesAction::Opened)
+            || !event.issue.is_pr()
+        {
             return Ok(None);
         }
-        Ok(Some(diff)) => diff,
-    };
-    Ok(Some(AssignInput { git_diff }))
-}
+        let git_diff = match event.issue.diff(&ctx.github).await {
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                log::error!("failed to fetch diff: {:?}", e);
+                return Ok(None);
+            }
+            Ok(Some(diff)) => diff.to_string(),
+        };
+        Ok(Some(AssignInput { git_diff }))
+    }
 
-/// Handles the work of setting an assignment for a new PR and posting a
-/// welcome message.
-pub(super) async fn handle_input(
-    ctx: &Context,
-    config: &AssignConfig,
-    event: &IssuesEvent,
-    input: AssignInput,
-) -> anyhow::Result<()> {
-    // Don't auto-assign or welcome if the user manually set the assignee when opening.
-    if event.issue.assignees.is_empty() {
-        let (assignee, from_comment) = determine_assignee(ctx, event, config, &input).await?;
-        if assignee.as_deref() == Some("ghost") {
-            // "ghost" is GitHub's placeholder account for deleted accounts.
-            // It is used here as a convenient way to prevent assignment. This
-            // is typically used for rollups or experiments where you don't
-            // want any assignments or noise.
-            return Ok(());
-        }
-        let welcome = if ctx
-            .github
-            .is_new_contributor(&event.repository, &event.issue.user.login)
-            .await
-        {
-            let who_text = match &assignee {
-                Some(assignee) => WELCOME_WITH_REVIEWER.replace("{assignee}", assignee),
-                None => WELCOME_WITHOUT_REVIEWER.to_string(),
-            };
-            let mut welcome = NEW_USER_WELCOME_MESSAGE.replace("{who}", &who_text);
-            if let Some(contrib) = &config.contributing_url {
-                welcome.push_str("\n\n");
-                welcome.push_str(
-                    &CONTRIBUTION_MESSAGE
-                        .replace("{contributing_url}", contrib)
-                        .replace("{bot}", &ctx.username),
-                );
+    /// Handles the work of setting an assignment for a new PR and posting a
+    /// welcome message.
+    async fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &AssignConfig,
+        event: &IssuesEvent,
+        input: AssignInput,
+    ) -> anyhow::Result<()> {
+        // Don't auto-assign or welcome if the user manually set the assignee when opening.
+        if event.issue.assignees.is_empty() {
+            let (assignee, from_comment) = determine_assignee(ctx, event, config, &input).await?;
+            if assignee.as_deref() == Some("ghost") {
+                // "ghost" is GitHub's placeholder account for deleted accounts.
+                // It is used here as a convenient way to prevent assignment. This
+                // is typically used for rollups or experiments where you don't
+                // want any assignments or noise.
+                return Ok(());
             }
-            Some(welcome)
-        } else if !from_comment {
-            let welcome = match &assignee {
-                Some(assignee) => RETURNING_USER_WELCOME_MESSAGE
-                    .replace("{assignee}", assignee)
-                    .replace("{bot}", &ctx.username),
-                None => RETURNING_USER_WELCOME_MESSAGE_NO_REVIEWER
-                    .replace("{author}", &event.issue.user.login),
+            let welcome = if event
+                .issue
+                .is_new_contributor(&ctx.github, &event.repository)
+                .await
+            {
+                let who_text = match &assignee {
+                    Some(assignee) => WELCOME_WITH_REVIEWER.replace("{assignee}", assignee),
+                    None => WELCOME_WITHOUT_REVIEWER.to_string(),
+                };
+                let mut welcome = NEW_USER_WELCOME_MESSAGE.replace("{who}", &who_text);
+                if let Some(contrib) = &config.contributing_url {
+                    welcome.push_str("\n\n");
+                    welcome.push_str(
+                        &CONTRIBUTION_MESSAGE
+                            .replace("{contributing_url}", contrib)
+                            .replace("{bot}", &ctx.username),
+                    );
+                }
+                Some(welcome)
+            } else if !from_comment {
+                let welcome = match &assignee {
+                    Some(assignee) => RETURNING_USER_WELCOME_MESSAGE
+                        .replace("{assignee}", assignee)
+                        .replace("{bot}", &ctx.username),
+                    None => RETURNING_USER_WELCOME_MESSAGE_NO_REVIEWER
+                        .replace("{author}", &event.issue.user.login),
+                };
+                Some(welcome)
+            } else {
+                // No welcome is posted if they are not new and they used `r?` in the opening body.
+                None
             };
-            Some(welcome)
-        } else {
-            // No welcome is posted if they are not new and they used `r?` in the opening body.
-            None
-        };
-        if let Some(assignee) = assignee {
-            set_assignee(&event.issue, &ctx.github, &assignee).await;
-        }
+            if let Some(assignee) = assignee {
+                set_assignee(&event.issue, &ctx.github, &assignee).await;
+            }
 
-        if let Some(welcome) = welcome {
-            if let Err(e) = event.issue.post_comment(&ctx.github, &welcome).await {
-                log::warn!(
-                    "failed to post welcome comment to {}: {e}",
-                    event.issue.global_id()
-                );
+            if let Some(welcome) = welcome {
+                if let Err(e) = event.issue.post_comment(&ctx.github, &welcome).await {
+                    log::warn!(
+                        "failed to post welcome comment to {}: {e}",
+                        event.issue.global_id()
+                    );
+                }
             }
         }
-    }
 
-    // Compute some warning messages to post to new PRs.
-    let mut warnings = Vec::new();
-    if config.warn_non_default_branch {
-        warnings.extend(non_default_branch(event));
+        // Compute some warning messages to post to new PRs.
+        let mut warnings = Vec::new();
+        if config.warn_non_default_branch {
+            warnings.extend(non_default_branch(event));
+        }
+        warnings.extend(modifies_submodule(&input.git_diff));
+        if !warnings.is_empty() {
+            let warnings: Vec<_> = warnings
+                .iter()
+                .map(|warning| format!("* {warning}"))
+                .collect();
+            let warning = format!(":warning: **Warning** :warning:\n\n{}", warnings.join("\n"));
+            event.issue.post_comment(&ctx.github, &warning).await?;
+        };
+        Ok(())
     }
-    warnings.extend(modifies_submodule(&input.git_diff));
-    if !warnings.is_empty() {
-        let warnings: Vec<_> = warnings
-            .iter()
-            .map(|warning| format!("* {warning}"))
-            .collect();
-        let warning = format!(":warning: **Warning** :warning:\n\n{}", warnings.join("\n"));
-        event.issue.post_comment(&ctx.github, &warning).await?;
-    };
-    Ok(())
 }
 
 /// Finds the `r?` command in the PR body.
@@ -420,183 +435,228 @@ fn find_reviewers_from_diff(config: &AssignConfig, diff: &str) -> anyhow::Result
     Ok(potential)
 }
 
-/// Handles a command posted in a comment.
-pub(super) async fn handle_command(
-    ctx: &Context,
-    config: &AssignConfig,
-    event: &Event,
-    cmd: AssignCommand,
-) -> anyhow::Result<()> {
-    let is_team_member = if let Err(_) | Ok(false) = event.user().is_team_member(&ctx.github).await
-    {
-        false
-    } else {
-        true
-    };
+#[async_trait]
+impl CommandHandler for Handler {
+    type Config = AssignConfig;
+    type Command = AssignCommand;
+
+    const NAME: &'static str = "assign_command";
+
+    fn usage(_config: &AssignConfig, bot: &str) -> Vec<Usage> {
+        vec![
+            Usage::new(
+                format!("@{bot} claim"),
+                "assigns you to the issue.",
+                Permission::Anyone,
+            ),
+            Usage::new(
+                format!("@{bot} assign @<user>"),
+                "assigns someone. Only team members can assign someone other than themselves.",
+                Permission::Described,
+            ),
+            Usage::new(
+                format!("@{bot} release-assignment"),
+                "removes the assignment. Only the assignee and team members can use it.",
+                Permission::Described,
+            ),
+            Usage::new(
+                "r? @<user>",
+                "requests a review from someone, or from a team with `r? <team>`.",
+                Permission::Anyone,
+            ),
+        ]
+    }
 
-    // Don't handle commands in comments from the bot. Some of the comments it
-    // posts contain commands to instruct the user, not things that the bot
-    // should respond to.
-    if event.user().login == ctx.username.as_str() {
-        return Ok(());
+    fn permission(command: &AssignCommand) -> Permission {
+        match command {
+            AssignCommand::Own | AssignCommand::ReviewName { .. } => Permission::Anyone,
+            AssignCommand::User { .. } | AssignCommand::Release => Permission::Described,
+        }
     }
 
-    let issue = event.issue().unwrap();
-    if issue.is_pr() {
-        if !issue.is_open() {
-            issue
-                .post_comment(&ctx.github, "Assignment is not allowed on a closed PR.")
-                .await?;
+    /// Handles a command posted in a comment.
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        config: &AssignConfig,
+        event: &Event,
+        cmd: AssignCommand,
+    ) -> anyhow::Result<()> {
+        let is_team_member =
+            if let Err(_) | Ok(false) = event.user().is_team_member(&ctx.github).await {
+                false
+            } else {
+                true
+            };
+
+        // Don't handle commands in comments from the bot. Some of the comments it
+        // posts contain commands to instruct the user, not things that the bot
+        // should respond to.
+        if event.user().login == ctx.username.as_str() {
             return Ok(());
         }
-        let username = match cmd {
-            AssignCommand::Own => event.user().login.clone(),
-            AssignCommand::User { username } => {
-                // Allow users on vacation to assign themselves to a PR, but not anyone else.
-                if config.is_on_vacation(&username)
-                    && event.user().login.to_lowercase() != username.to_lowercase()
-                {
-                    // This is a comment, so there must already be a reviewer assigned. No need to assign anyone else.
-                    issue
-                        .post_comment(&ctx.github, &on_vacation_msg(&username))
-                        .await?;
-                    return Ok(());
-                }
-                username
-            }
-            AssignCommand::Release => {
-                log::trace!(
-                    "ignoring release on PR {:?}, must always have assignee",
-                    issue.global_id()
-                );
+
+        let issue = event.issue().unwrap();
+        if issue.is_pr() {
+            if !issue.is_open() {
+                issue
+                    .post_comment(&ctx.github, "Assignment is not allowed on a closed PR.")
+                    .await?;
                 return Ok(());
             }
-            AssignCommand::ReviewName { name } => {
-                if config.owners.is_empty() {
-                    // To avoid conflicts with the highfive bot while transitioning,
-                    // r? is ignored if `owners` is not configured in triagebot.toml.
-                    return Ok(());
+            let username = match cmd {
+                AssignCommand::Own => event.user().login.clone(),
+                AssignCommand::User { username } => {
+                    // Allow users on vacation to assign themselves to a PR, but not anyone else.
+                    if config.is_on_vacation(&username)
+                        && event.user().login.to_lowercase() != username.to_lowercase()
+                    {
+                        // This is a comment, so there must already be a reviewer assigned. No need to assign anyone else.
+                        issue
+                            .post_comment(&ctx.github, &on_vacation_msg(&username))
+                            .await?;
+                        return Ok(());
+                    }
+                    username
                 }
-                if matches!(
-                    event,
-                    Event::Issue(IssuesEvent {
-                        action: IssuesAction::Opened,
-                        ..
-                    })
-                ) {
-                    // Don't handle r? comments on new PRs. Those will be
-                    // handled by the new PR trigger (which also handles the
-                    // welcome message).
+                AssignCommand::Release => {
+                    log::trace!(
+                        "ignoring release on PR {:?}, must always have assignee",
+                        issue.global_id()
+                    );
                     return Ok(());
                 }
-                if is_self_assign(&name, &event.user().login) {
-                    name.to_string()
-                } else {
-                    let teams = crate::team_data::teams(&ctx.github).await?;
-                    // remove "t-" or "T-" prefixes before checking if it's a team name
-                    let team_name = name.trim_start_matches("t-").trim_start_matches("T-");
-                    // Determine if assignee is a team. If yes, add the corresponding GH label.
-                    if teams.teams.get(team_name).is_some() {
-                        let t_label = format!("T-{}", &team_name);
-                        if let Err(err) = issue
-                            .add_labels(&ctx.github, vec![github::Label { name: t_label }])
-                            .await
-                        {
-                            if let Some(github::UnknownLabels { .. }) = err.downcast_ref() {
-                                log::warn!("Error assigning label: {}", err);
-                            } else {
-                                return Err(err);
+                AssignCommand::ReviewName { name } => {
+                    if config.owners.is_empty() {
+                        // To avoid conflicts with the highfive bot while transitioning,
+                        // r? is ignored if `owners` is not configured in triagebot.toml.
+                        return Ok(());
+                    }
+                    if matches!(
+                        event,
+                        Event::Issue(IssuesEvent {
+                            action: IssuesAction::Opened,
+                            ..
+                        })
+                    ) {
+                        // Don't handle r? comments on new PRs. Those will be
+                        // handled by the new PR trigger (which also handles the
+                        // welcome message).
+                        return Ok(());
+                    }
+                    if is_self_assign(&name, &event.user().login) {
+                        name.to_string()
+                    } else {
+                        let teams = crate::team_data::teams(&ctx.github).await?;
+                        // remove "t-" or "T-" prefixes before checking if it's a team name
+                        let team_name = name.trim_start_matches("t-").trim_start_matches("T-");
+                        // Determine if assignee is a team. If yes, add the corresponding GH label.
+                        if teams.teams.get(team_name).is_some() {
+                            let t_label = format!("T-{}", &team_name);
+                            if let Err(err) = issue
+                                .add_labels(&ctx.github, vec![github::Label { name: t_label }])
+                                .await
+                            {
+                                if let Some(github::UnknownLabels { .. }) = err.downcast_ref() {
+                                    log::warn!("Error assigning label: {}", err);
+                                } else {
+                                    return Err(err);
+                                }
                             }
                         }
-                    }
 
-                    match find_reviewer_from_names(&teams, config, issue, &[team_name.to_string()])
-                    {
-                        Ok(assignee) => assignee,
-                        Err(e) => {
-                            issue.post_comment(&ctx.github, &e.to_string()).await?;
-                            return Ok(());
+                        match find_reviewer_from_names(
+                            &teams,
+                            config,
+                            issue,
+                            &[team_name.to_string()],
+                        ) {
+                            Ok(assignee) => assignee,
+                            Err(e) => {
+                                issue.post_comment(&ctx.github, &e.to_string()).await?;
+                                return Ok(());
+                            }
                         }
                     }
                 }
-            }
-        };
-        set_assignee(issue, &ctx.github, &username).await;
-        return Ok(());
-    }
-
-    let e = EditIssueBody::new(&issue, "ASSIGN");
-
-    let to_assign = match cmd {
-        AssignCommand::Own => event.user().login.clone(),
-        AssignCommand::User { username } => {
-            if !is_team_member && username != event.user().login {
-                bail!("Only Rust team members can assign other users");
-            }
-            username.clone()
-        }
-        AssignCommand::Release => {
-            if let Some(AssignData {
-                user: Some(current),
-            }) = e.current_data()
-            {
-                if current == event.user().login || is_team_member {
-                    issue.remove_assignees(&ctx.github, Selection::All).await?;
-                    e.apply(&ctx.github, String::new(), AssignData { user: None })
-                        .await?;
-                    return Ok(());
-                } else {
-                    bail!("Cannot release another user's assignment");
-                }
-            } else {
-                let current = &event.user().login;
-                if issue.contain_assignee(current) {
-                    issue
-                        .remove_assignees(&ctx.github, Selection::One(&current))
-                        .await?;
-                    e.apply(&ctx.github, String::new(), AssignData { user: None })
-                        .await?;
-                    return Ok(());
-                } else {
-                    bail!("Cannot release unassigned issue");
-                }
             };
+            set_assignee(issue, &ctx.github, &username).await;
+            return Ok(());
         }
-        AssignCommand::ReviewName { .. } => bail!("r? is only allowed on PRs."),
-    };
-    // Don't re-assign if aleady assigned, e.g. on comment edit
-    if issue.contain_assignee(&to_assign) {
-        log::trace!(
-            "ignoring assign issue {} to {}, already assigned",
-            issue.global_id(),
-            to_assign,
-        );
-        return Ok(());
-    }
-    let data = AssignData {
-        user: Some(to_assign.clone()),
-    };
 
-    e.apply(&ctx.github, String::new(), &data).await?;
+        let e = EditIssueBody::new(&issue, "ASSIGN");
 
-    match issue.set_assignee(&ctx.github, &to_assign).await {
-        Ok(()) => return Ok(()), // we are done
-        Err(github::AssignmentError::InvalidAssignee) => {
-            issue
-                .set_assignee(&ctx.github, &ctx.username)
-                .await
-                .context("self-assignment failed")?;
-            let cmt_body = format!(
-                "This issue has been assigned to @{} via [this comment]({}).",
+        let to_assign = match cmd {
+            AssignCommand::Own => event.user().login.clone(),
+            AssignCommand::User { username } => {
+                if !is_team_member && username != event.user().login {
+                    bail!("Only Rust team members can assign other users");
+                }
+                username.clone()
+            }
+            AssignCommand::Release => {
+                if let Some(AssignData {
+                    user: Some(current),
+                }) = e.current_data()
+                {
+                    if current == event.user().login || is_team_member {
+                        issue.remove_assignees(&ctx.github, Selection::All).await?;
+                        e.apply(&ctx.github, String::new(), AssignData { user: None })
+                            .await?;
+                        return Ok(());
+                    } else {
+                        bail!("Cannot release another user's assignment");
+                    }
+                } else {
+                    let current = &event.user().login;
+                    if issue.contain_assignee(current) {
+                        issue
+                            .remove_assignees(&ctx.github, Selection::One(&current))
+                            .await?;
+                        e.apply(&ctx.github, String::new(), AssignData { user: None })
+                            .await?;
+                        return Ok(());
+                    } else {
+                        bail!("Cannot release unassigned issue");
+                    }
+                };
+            }
+            AssignCommand::ReviewName { .. } => bail!("r? is only allowed on PRs."),
+        };
+        // Don't re-assign if aleady assigned, e.g. on comment edit
+        if issue.contain_assignee(&to_assign) {
+            log::trace!(
+                "ignoring assign issue {} to {}, already assigned",
+                issue.global_id(),
                 to_assign,
-                event.html_url().unwrap()
             );
-            e.apply(&ctx.github, cmt_body, &data).await?;
+            return Ok(());
         }
-        Err(e) => return Err(e.into()),
-    }
+        let data = AssignData {
+            user: Some(to_assign.clone()),
+        };
 
-    Ok(())
+        e.apply(&ctx.github, String::new(), &data).await?;
+
+        match issue.set_assignee(&ctx.github, &to_assign).await {
+            Ok(()) => return Ok(()), // we are done
+            Err(github::AssignmentError::InvalidAssignee) => {
+                issue
+                    .set_assignee(&ctx.github, &ctx.username)
+                    .await
+                    .context("self-assignment failed")?;
+                let cmt_body = format!(
+                    "This issue has been assigned to @{} via [this comment]({}).",
+                    to_assign,
+                    event.html_url().unwrap()
+                );
+                e.apply(&ctx.github, cmt_body, &data).await?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -1,25 +1,48 @@
 //! Allows to close an issue or a PR
 
-use crate::{config::CloseConfig, github::Event, handlers::Context, interactions::ErrorComment};
+use crate::{
+    config::CloseConfig,
+    github::Event,
+    handlers::{CommandHandler, Context, Permission, Usage},
+};
+use async_trait::async_trait;
 use parser::command::close::CloseCommand;
 
-pub(super) async fn handle_command(
-    ctx: &Context,
-    _config: &CloseConfig,
-    event: &Event,
-    _cmd: CloseCommand,
-) -> anyhow::Result<()> {
-    let issue = event.issue().unwrap();
-    let is_team_member = event
-        .user()
-        .is_team_member(&ctx.github)
-        .await
-        .unwrap_or(false);
-    if !is_team_member {
-        let cmnt = ErrorComment::new(&issue, "Only team members can close issues.");
-        cmnt.post(&ctx.github).await?;
-        return Ok(());
+const PERMISSION: Permission = Permission::TeamMembers {
+    silent: false,
+    message: "Only team members can close issues.",
+};
+
+pub(super) struct Handler;
+
+#[async_trait]
+impl CommandHandler for Handler {
+    type Config = CloseConfig;
+    type Command = CloseCommand;
+
+    const NAME: &'static str = "close";
+
+    fn usage(_config: &CloseConfig, bot: &str) -> Vec<Usage> {
+        vec![Usage::new(
+            format!("@{bot} close"),
+            "closes the issue.",
+            PERMISSION,
+        )]
+    }
+
+    fn permission(_command: &CloseCommand) -> Permission {
+        PERMISSION
+    }
+
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        _config: &CloseConfig,
+        event: &Event,
+        _cmd: CloseCommand,
+    ) -> anyhow::Result<()> {
+        let issue = event.issue().unwrap();
+        issue.close(&ctx.github).await?;
+        Ok(())
     }
-    issue.close(&ctx.github).await?;
-    Ok(())
 }
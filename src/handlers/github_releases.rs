@@ -1,7 +1,7 @@
 use crate::{
     changelogs::Changelog,
     config::GitHubReleasesConfig,
-    github::{CreateEvent, CreateKind, Event},
+    github::{retry, CreateEvent, CreateKind, Event},
     handlers::Context,
 };
 use anyhow::Context as _;
@@ -24,6 +24,12 @@ pub(super) async fn handle(
         _ => return Ok(()),
     }
 
+    // Releases are written through octocrab, which dry-run mode can't skip.
+    if ctx.github.is_dry_run() {
+        log::info!("dry run, not handling github releases");
+        return Ok(());
+    }
+
     log::info!("handling github releases");
 
     log::debug!("loading the changelog");
@@ -66,33 +72,27 @@ pub(super) async fn handle(
             if let Some(release) = releases.get(tag) {
                 if release.name != expected_name || release.body != expected_body {
                     log::info!("updating release {} on {}", tag, event.repo().full_name);
-                    let _: serde_json::Value = ctx
-                        .octocrab
-                        .patch(
-                            &release.url,
-                            Some(&serde_json::json!({
-                                "name": expected_name,
-                                "body": expected_body,
-                            })),
-                        )
-                        .await?;
+                    let body = serde_json::json!({
+                        "name": expected_name,
+                        "body": expected_body,
+                    });
+                    let _: serde_json::Value =
+                        retry::octocrab(true, || ctx.octocrab.patch(&release.url, Some(&body)))
+                            .await?;
                 } else {
                     // Avoid waiting for the delay below.
                     continue;
                 }
             } else {
                 log::info!("creating release {} on {}", tag, event.repo().full_name);
-                let e: octocrab::Result<serde_json::Value> = ctx
-                    .octocrab
-                    .post(
-                        format!("/repos/{}/releases", event.repo().full_name),
-                        Some(&serde_json::json!({
-                            "tag_name": tag,
-                            "name": expected_name,
-                            "body": expected_body,
-                        })),
-                    )
-                    .await;
+                let url = format!("/repos/{}/releases", event.repo().full_name);
+                let body = serde_json::json!({
+                    "tag_name": tag,
+                    "name": expected_name,
+                    "body": expected_body,
+                });
+                let e: octocrab::Result<serde_json::Value> =
+                    retry::octocrab(false, || ctx.octocrab.post(&url, Some(&body))).await;
                 match e {
                     Ok(v) => log::debug!("created release: {:?}", v),
                     Err(e) => {
@@ -141,11 +141,10 @@ where
     R: Eq + PartialEq + std::hash::Hash,
     F: Fn(&T) -> R,
 {
-    let mut current_page: Page<T> = ctx
-        .octocrab
-        .get::<Page<T>, _, ()>(url, None)
-        .await
-        .with_context(|| format!("failed to load {url}"))?;
+    let mut current_page: Page<T> =
+        retry::octocrab(true, || ctx.octocrab.get::<Page<T>, _, ()>(url, None))
+            .await
+            .with_context(|| format!("failed to load {url}"))?;
 
     let mut items = current_page
         .take_items()
@@ -153,11 +152,10 @@ where
         .map(|val| (key(&val), val))
         .collect::<HashMap<R, T>>();
 
-    while let Some(mut new_page) = ctx
-        .octocrab
-        .get_page::<T>(&current_page.next)
-        .await
-        .with_context(|| format!("failed to load next page {:?}", current_page.next))?
+    while let Some(mut new_page) =
+        retry::octocrab(true, || ctx.octocrab.get_page::<T>(&current_page.next))
+            .await
+            .with_context(|| format!("failed to load next page {:?}", current_page.next))?
     {
         items.extend(
             new_page
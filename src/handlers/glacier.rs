@@ -1,78 +1,109 @@
 //! Allows team members to directly create a glacier PR with the code provided.
 
-use crate::{config::GlacierConfig, github::Event, handlers::Context};
+use crate::{
+    config::GlacierConfig,
+    github::{retry, Event},
+    handlers::{CommandHandler, Context, Permission, Usage},
+};
+use async_trait::async_trait;
 use models::repos::Object;
 use octocrab::models;
 use octocrab::params::repos::Reference;
 use parser::command::glacier::GlacierCommand;
 use tracing as log;
 
-pub(super) async fn handle_command(
-    ctx: &Context,
-    _config: &GlacierConfig,
-    event: &Event,
-    cmd: GlacierCommand,
-) -> anyhow::Result<()> {
-    let is_team_member = event
-        .user()
-        .is_team_member(&ctx.github)
-        .await
-        .unwrap_or(false);
+const PERMISSION: Permission = Permission::TeamMembers {
+    silent: true,
+    message: "Only team members can open glacier pull requests.",
+};
 
-    if !is_team_member {
-        return Ok(());
-    };
+pub(super) struct Handler;
 
-    let body = ctx
-        .github
-        .raw_gist_from_url(&cmd.source, "playground.rs")
-        .await?;
+#[async_trait]
+impl CommandHandler for Handler {
+    type Config = GlacierConfig;
+    type Command = GlacierCommand;
+
+    const NAME: &'static str = "glacier";
+
+    fn usage(_config: &GlacierConfig, bot: &str) -> Vec<Usage> {
+        vec![Usage::new(
+            format!("@{bot} glacier <gist-url>"),
+            "opens a pull request adding the code to glacier.",
+            PERMISSION,
+        )]
+    }
+
+    fn permission(_command: &GlacierCommand) -> Permission {
+        PERMISSION
+    }
 
-    let number = event.issue().unwrap().number;
-    let user = event.user();
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        _config: &GlacierConfig,
+        event: &Event,
+        cmd: GlacierCommand,
+    ) -> anyhow::Result<()> {
+        // The pull request is opened through octocrab, which dry-run mode can't
+        // skip.
+        if ctx.github.is_dry_run() {
+            log::info!("dry run, not opening a glacier pull request");
+            return Ok(());
+        }
 
-    let octocrab = &ctx.octocrab;
+        let body = ctx
+            .github
+            .raw_gist_from_url(&cmd.source, "playground.rs")
+            .await?;
 
-    let fork = octocrab.repos(&ctx.username, "glacier");
-    let base = octocrab.repos("rust-lang", "glacier");
+        let number = event.issue().unwrap().number;
+        let user = event.user();
 
-    let master = base
-        .get_ref(&Reference::Branch("master".to_string()))
-        .await?
-        .object;
-    let master = if let Object::Commit { sha, .. } = master {
-        sha
-    } else {
-        log::error!("invalid commit sha - master {:?}", master);
-        unreachable!()
-    };
+        let octocrab = &ctx.octocrab;
 
-    fork.create_ref(
-        &Reference::Branch(format!("triagebot-ice-{}", number)),
-        master,
-    )
-    .await?;
-    fork.create_file(
-        format!("ices/{}.rs", number),
-        format!("Add ICE reproduction for issue rust-lang/rust#{}.", number),
-        body,
-    )
-    .branch(format!("triagebot-ice-{}", number))
-    .send()
-    .await?;
+        let fork = octocrab.repos(&ctx.username, "glacier");
+        let base = octocrab.repos("rust-lang", "glacier");
+
+        let master_ref = Reference::Branch("master".to_string());
+        let master = retry::octocrab(true, || base.get_ref(&master_ref))
+            .await?
+            .object;
+        let master = if let Object::Commit { sha, .. } = master {
+            sha
+        } else {
+            log::error!("invalid commit sha - master {:?}", master);
+            unreachable!()
+        };
+
+        let branch = Reference::Branch(format!("triagebot-ice-{}", number));
+        retry::octocrab(false, || fork.create_ref(&branch, master.clone())).await?;
+        retry::octocrab(false, || {
+            fork.create_file(
+                format!("ices/{}.rs", number),
+                format!("Add ICE reproduction for issue rust-lang/rust#{}.", number),
+                body.clone(),
+            )
+            .branch(format!("triagebot-ice-{}", number))
+            .send()
+        })
+        .await?;
 
-    octocrab
-        .pulls("rust-lang", "glacier")
-        .create(
-            format!("ICE - rust-lang/rust#{}", number),
-            format!("{}:triagebot-ice-{}", ctx.username, number),
-            "master",
-        )
-        .body(format!(
-            "Automatically created by @{} in issue rust-lang/rust#{}",
-            user.login, number
-        ))
-        .send()
+        retry::octocrab(false, || {
+            octocrab
+                .pulls("rust-lang", "glacier")
+                .create(
+                    format!("ICE - rust-lang/rust#{}", number),
+                    format!("{}:triagebot-ice-{}", ctx.username, number),
+                    "master",
+                )
+                .body(format!(
+                    "Automatically created by @{} in issue rust-lang/rust#{}",
+                    user.login, number
+                ))
+                .send()
+        })
         .await?;
-    Ok(())
+        Ok(())
+    }
 }
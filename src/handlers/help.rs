@@ -0,0 +1,111 @@
+//! Purpose: Allow any user to find out which commands are available in a
+//! repository with `@rustbot help`.
+//!
+//! The reply lists the commands of each feature enabled in `triagebot.toml`,
+//! and who may use them.
+//!
+//! Parsing is done in the `parser::command::help` module.
+
+use crate::{
+    config::Config,
+    github::Event,
+    handlers::{CommandHandler, Context, Permission, Usage},
+};
+use async_trait::async_trait;
+use parser::command::help::HelpCommand;
+
+pub(super) struct Handler;
+
+#[async_trait]
+impl CommandHandler for Handler {
+    type Config = Config;
+    type Command = HelpCommand;
+
+    const NAME: &'static str = "help";
+
+    fn usage(_config: &Config, bot: &str) -> Vec<Usage> {
+        vec![Usage::new(
+            format!("@{bot} help"),
+            "shows this message.",
+            Permission::Anyone,
+        )]
+    }
+
+    fn permission(_command: &HelpCommand) -> Permission {
+        Permission::Anyone
+    }
+
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        config: &Config,
+        event: &Event,
+        _cmd: HelpCommand,
+    ) -> anyhow::Result<()> {
+        let issue = event.issue().unwrap();
+        issue
+            .post_comment(&ctx.github, &help_message(config, &ctx.username))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Formats a list of names as inline code, e.g. "`a`, `b`".
+pub(super) fn code_list<'a>(names: impl IntoIterator<Item = &'a str>) -> String {
+    let mut names: Vec<_> = names.into_iter().map(|name| format!("`{name}`")).collect();
+    names.sort();
+    names.join(", ")
+}
+
+fn help_message(config: &Config, bot: &str) -> String {
+    let commands: Vec<_> = super::usage(config, bot)
+        .into_iter()
+        .map(|usage| {
+            let who = match usage.permission {
+                Permission::Anyone => " Anyone can use it.",
+                Permission::TeamMembers { .. } => " Only team members can use it.",
+                Permission::Described => "",
+            };
+            format!("- `{}`: {}{who}", usage.syntax, usage.description)
+        })
+        .collect();
+
+    format!(
+        "The following commands are available in this repository:\n\n{}",
+        commands.join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_enabled_commands() {
+        let config: Config = toml::from_str(
+            r#"
+            [relabel]
+            allow-unauthenticated = ["A-*", "!A-secret"]
+
+            [ping.compiler]
+            message = "cc"
+            alias = ["c"]
+
+            [close]
+            "#,
+        )
+        .unwrap();
+        let message = help_message(&config, "rustbot");
+        assert_eq!(
+            message,
+            "The following commands are available in this repository:\n\n\
+             - `@rustbot label +<label> -<label>`: adds or removes labels. Anyone can change \
+             labels matching `A-*` (except `A-secret`); other labels can only be changed by \
+             team members.\n\
+             - `@rustbot ping <team>`: notifies a team. The teams are `compiler` (also `c`). \
+             Only team members can use it.\n\
+             - `@rustbot close`: closes the issue. Only team members can use it.\n\
+             - `@rustbot help`: shows this message. Anyone can use it."
+        );
+    }
+}
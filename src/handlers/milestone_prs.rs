@@ -70,7 +70,8 @@ async fn get_version_standalone(ctx: &Context, merge_sha: &str) -> anyhow::Resul
         .github
         .raw()
         .get(&format!(
-            "https://raw.githubusercontent.com/rust-lang/rust/{}/src/version",
+            "{}/rust-lang/rust/{}/src/version",
+            crate::github::raw_url(),
             merge_sha
         ))
         .send()
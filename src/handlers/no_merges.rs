@@ -5,9 +5,10 @@ use crate::{
     config::NoMergesConfig,
     db::issue_data::IssueData,
     github::{IssuesAction, IssuesEvent, Label},
-    handlers::Context,
+    handlers::{Context, IssueHandler},
 };
 use anyhow::Context as _;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt::Write;
@@ -26,57 +27,149 @@ struct NoMergesState {
     mentioned_merge_commits: HashSet<String>,
 }
 
-pub(super) async fn parse_input(
-    ctx: &Context,
-    event: &IssuesEvent,
-    config: Option<&NoMergesConfig>,
-) -> Result<Option<NoMergesInput>, String> {
-    if !matches!(
-        event.action,
-        IssuesAction::Opened | IssuesAction::Synchronize | IssuesAction::ReadyForReview
-    ) {
-        return Ok(None);
-    }
+pub(super) struct Handler;
+
+#[async_trait]
+impl IssueHandler for Handler {
+    type Config = NoMergesConfig;
+    type Input = NoMergesInput;
+
+    const NAME: &'static str = "no_merges";
+
+    async fn parse_input(
+        &self,
+        ctx: &Context,
+        event: &IssuesEvent,
+        config: Option<&NoMergesConfig>,
+    ) -> Result<Option<NoMergesInput>, String> {
+        if !matches!(
+            event.action,
+            IssuesAction::Opened | IssuesAction::Synchronize | IssuesAction::ReadyForReview
+        ) {
+            return Ok(None);
+        }
 
-    // Require a `[no_merges]` configuration block to enable no-merges notifications.
-    let Some(config) = config else {
-        return Ok(None);
-    };
+        // Require a `[no_merges]` configuration block to enable no-merges notifications.
+        let Some(config) = config else {
+            return Ok(None);
+        };
 
-    // Don't ping on rollups or draft PRs.
-    if event.issue.title.starts_with("Rollup of") || event.issue.draft {
-        return Ok(None);
-    }
+        // Don't ping on rollups or draft PRs.
+        if event.issue.title.starts_with("Rollup of") || event.issue.draft {
+            return Ok(None);
+        }
 
-    // Don't trigger if the PR has any of the excluded title segments.
-    if config
-        .exclude_titles
-        .iter()
-        .any(|s| event.issue.title.contains(s))
-    {
-        return Ok(None);
-    }
+        // Don't trigger if the PR has any of the excluded title segments.
+        if config
+            .exclude_titles
+            .iter()
+            .any(|s| event.issue.title.contains(s))
+        {
+            return Ok(None);
+        }
 
-    let mut merge_commits = HashSet::new();
-    let commits = event
-        .issue
-        .commits(&ctx.github)
-        .await
-        .map_err(|e| {
-            log::error!("failed to fetch commits: {:?}", e);
-        })
-        .unwrap_or_default();
-    for commit in commits {
-        if commit.parents.len() > 1 {
-            merge_commits.insert(commit.sha.clone());
+        let mut merge_commits = HashSet::new();
+        let commits = event
+            .issue
+            .commits(&ctx.github)
+            .await
+            .map_err(|e| {
+                log::error!("failed to fetch commits: {:?}", e);
+            })
+            .unwrap_or_default();
+        for commit in commits {
+            if commit.parents.len() > 1 {
+                merge_commits.insert(commit.sha.clone());
+            }
+        }
+
+        if merge_commits.is_empty() {
+            return Ok(None);
         }
-    }
 
-    if merge_commits.is_empty() {
-        return Ok(None);
+        Ok(Some(NoMergesInput { merge_commits }))
     }
 
-    Ok(Some(NoMergesInput { merge_commits }))
+    async fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &NoMergesConfig,
+        event: &IssuesEvent,
+        input: NoMergesInput,
+    ) -> anyhow::Result<()> {
+        let mut client = ctx.db.get().await;
+        let mut state: IssueData<'_, NoMergesState> =
+            IssueData::load(&mut client, &event.issue, NO_MERGES_KEY).await?;
+        let first_time = state.data.mentioned_merge_commits.is_empty();
+
+        let mut message = config
+            .message
+            .as_deref()
+            .unwrap_or(DEFAULT_MESSAGE)
+            .to_string();
+
+        let since_last_posted = if first_time {
+            ""
+        } else {
+            " (since this message was last posted)"
+        };
+        writeln!(
+            message,
+            "The following commits are merge commits{since_last_posted}:"
+        )
+        .unwrap();
+
+        let mut should_send = false;
+        for commit in &input.merge_commits {
+            if state.data.mentioned_merge_commits.contains(commit) {
+                continue;
+            }
+
+            should_send = true;
+            state.data.mentioned_merge_commits.insert((*commit).clone());
+            writeln!(message, "- {commit}").unwrap();
+        }
+
+        if should_send {
+            if !first_time {
+                // Check if the labels are still set.
+                // Otherwise, they were probably removed manually.
+                let any_removed = config.labels.iter().any(|label| {
+                    // No label on the issue matches.
+                    event.issue.labels().iter().all(|l| &l.name != label)
+                });
+
+                if any_removed {
+                    // Assume it was a false positive, so don't
+                    // re-add the labels or send a message this time.
+                    state.save().await?;
+                    return Ok(());
+                }
+            }
+
+            // Set labels
+            let labels = config
+                .labels
+                .iter()
+                .cloned()
+                .map(|name| Label { name })
+                .collect();
+            event
+                .issue
+                .add_labels(&ctx.github, labels)
+                .await
+                .context("failed to set no_merges labels")?;
+
+            // Post comment
+            event
+                .issue
+                .post_comment(&ctx.github, &message)
+                .await
+                .context("failed to post no_merges comment")?;
+            state.save().await?;
+        }
+        Ok(())
+    }
 }
 
 const DEFAULT_MESSAGE: &str = "
@@ -94,86 +187,6 @@ $ git push --force-with-lease
 
 ";
 
-pub(super) async fn handle_input(
-    ctx: &Context,
-    config: &NoMergesConfig,
-    event: &IssuesEvent,
-    input: NoMergesInput,
-) -> anyhow::Result<()> {
-    let mut client = ctx.db.get().await;
-    let mut state: IssueData<'_, NoMergesState> =
-        IssueData::load(&mut client, &event.issue, NO_MERGES_KEY).await?;
-    let first_time = state.data.mentioned_merge_commits.is_empty();
-
-    let mut message = config
-        .message
-        .as_deref()
-        .unwrap_or(DEFAULT_MESSAGE)
-        .to_string();
-
-    let since_last_posted = if first_time {
-        ""
-    } else {
-        " (since this message was last posted)"
-    };
-    writeln!(
-        message,
-        "The following commits are merge commits{since_last_posted}:"
-    )
-    .unwrap();
-
-    let mut should_send = false;
-    for commit in &input.merge_commits {
-        if state.data.mentioned_merge_commits.contains(commit) {
-            continue;
-        }
-
-        should_send = true;
-        state.data.mentioned_merge_commits.insert((*commit).clone());
-        writeln!(message, "- {commit}").unwrap();
-    }
-
-    if should_send {
-        if !first_time {
-            // Check if the labels are still set.
-            // Otherwise, they were probably removed manually.
-            let any_removed = config.labels.iter().any(|label| {
-                // No label on the issue matches.
-                event.issue.labels().iter().all(|l| &l.name != label)
-            });
-
-            if any_removed {
-                // Assume it was a false positive, so don't
-                // re-add the labels or send a message this time.
-                state.save().await?;
-                return Ok(());
-            }
-        }
-
-        // Set labels
-        let labels = config
-            .labels
-            .iter()
-            .cloned()
-            .map(|name| Label { name })
-            .collect();
-        event
-            .issue
-            .add_labels(&ctx.github, labels)
-            .await
-            .context("failed to set no_merges labels")?;
-
-        // Post comment
-        event
-            .issue
-            .post_comment(&ctx.github, &message)
-            .await
-            .context("failed to post no_merges comment")?;
-        state.save().await?;
-    }
-    Ok(())
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
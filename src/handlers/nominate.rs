@@ -3,89 +3,117 @@
 use crate::{
     config::NominateConfig,
     github::{self, Event},
-    handlers::Context,
+    handlers::{help::code_list, CommandHandler, Context, Permission, Usage},
     interactions::ErrorComment,
 };
-use parser::command::nominate::{NominateCommand, Style};
+use async_trait::async_trait;
+use parser::command::{
+    nominate::{NominateCommand, Style},
+    Command,
+};
 
-pub(super) async fn handle_command(
-    ctx: &Context,
-    config: &NominateConfig,
-    event: &Event,
-    cmd: NominateCommand,
-) -> anyhow::Result<()> {
-    let is_team_member = if let Err(_) | Ok(false) = event.user().is_team_member(&ctx.github).await
-    {
-        false
-    } else {
-        true
-    };
+const PERMISSION: Permission = Permission::TeamMembers {
+    silent: false,
+    message: "Nominating and approving issues and pull requests is restricted to members of the Rust teams.",
+};
 
-    if !is_team_member {
-        let cmnt = ErrorComment::new(
-            &event.issue().unwrap(),
-            format!(
-                "Nominating and approving issues and pull requests is restricted to members of\
-                 the Rust teams."
-            ),
-        );
-        cmnt.post(&ctx.github).await?;
-        return Ok(());
-    }
+pub(super) struct Handler;
 
-    let issue_labels = event.issue().unwrap().labels();
-    let mut labels_to_add = vec![];
-    if cmd.style == Style::BetaApprove {
-        if !issue_labels.iter().any(|l| l.name == "beta-nominated") {
-            let cmnt = ErrorComment::new(
-                &event.issue().unwrap(),
-                format!(
-                    "This pull request is not beta-nominated, so it cannot be approved yet.\
-                     Perhaps try to beta-nominate it by using `@{} beta-nominate <team>`?",
-                    ctx.username,
-                ),
-            );
-            cmnt.post(&ctx.github).await?;
-            return Ok(());
-        }
+#[async_trait]
+impl CommandHandler for Handler {
+    type Config = NominateConfig;
+    type Command = NominateCommand;
+
+    const NAME: &'static str = "nominate";
 
-        // Add the beta-accepted label, but don't attempt to remove beta-nominated or the team
-        // label.
-        labels_to_add.push(github::Label {
-            name: "beta-accepted".into(),
-        });
-    } else {
-        if !config.teams.contains_key(&cmd.team) {
-            let cmnt = ErrorComment::new(
-                &event.issue().unwrap(),
+    fn usage(config: &NominateConfig, bot: &str) -> Vec<Usage> {
+        vec![
+            Usage::new(
+                format!("@{bot} nominate <team>"),
                 format!(
-                    "This team (`{}`) cannot be nominated for via this command;\
-                     it may need to be added to `triagebot.toml` on the default branch.",
-                    cmd.team,
+                    "nominates the issue for a team meeting. The teams are {}.",
+                    code_list(config.teams.keys().map(|t| t.as_str()))
                 ),
-            );
-            cmnt.post(&ctx.github).await?;
-            return Ok(());
-        }
-
-        let label = config.teams[&cmd.team].clone();
-        labels_to_add.push(github::Label { name: label });
+                PERMISSION,
+            ),
+            Usage::new(
+                format!("@{bot} beta-nominate <team>"),
+                "nominates the pull request for a beta backport.",
+                PERMISSION,
+            ),
+            Usage::new(
+                format!("@{bot} beta-accept"),
+                "accepts a beta backport.",
+                PERMISSION,
+            ),
+        ]
+    }
 
-        let style_label = match cmd.style {
-            Style::Decision => "I-nominated",
-            Style::Beta => "beta-nominated",
-            Style::BetaApprove => unreachable!(),
-        };
-        labels_to_add.push(github::Label {
-            name: style_label.into(),
-        });
+    fn permission(_command: &NominateCommand) -> Permission {
+        PERMISSION
     }
 
-    event
-        .issue()
-        .unwrap()
-        .add_labels(&ctx.github, labels_to_add)
-        .await?;
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        config: &NominateConfig,
+        event: &Event,
+        cmd: NominateCommand,
+    ) -> anyhow::Result<()> {
+        let issue_labels = event.issue().unwrap().labels();
+        let mut labels_to_add = vec![];
+        if cmd.style == Style::BetaApprove {
+            if !issue_labels.iter().any(|l| l.name == "beta-nominated") {
+                let cmnt = ErrorComment::new(
+                    &event.issue().unwrap(),
+                    format!(
+                        "This pull request is not beta-nominated, so it cannot be approved yet.\
+                         Perhaps try to beta-nominate it by using `@{} beta-nominate <team>`?",
+                        ctx.username,
+                    ),
+                );
+                cmnt.post(&ctx.github).await?;
+                return Ok(());
+            }
+
+            // Add the beta-accepted label, but don't attempt to remove beta-nominated or the team
+            // label.
+            labels_to_add.push(github::Label {
+                name: "beta-accepted".into(),
+            });
+        } else {
+            if !config.teams.contains_key(&cmd.team) {
+                let cmnt = ErrorComment::new(
+                    &event.issue().unwrap(),
+                    format!(
+                        "This team (`{}`) cannot be nominated for via this command;\
+                         it may need to be added to `triagebot.toml` on the default branch.",
+                        cmd.team,
+                    ),
+                );
+                cmnt.post(&ctx.github).await?;
+                return Ok(());
+            }
 
-    Ok(())
+            let label = config.teams[&cmd.team].clone();
+            labels_to_add.push(github::Label { name: label });
+
+            let style_label = match cmd.style {
+                Style::Decision => "I-nominated",
+                Style::Beta => "beta-nominated",
+                Style::BetaApprove => unreachable!(),
+            };
+            labels_to_add.push(github::Label {
+                name: style_label.into(),
+            });
+        }
+
+        event
+            .issue()
+            .unwrap()
+            .add_labels(&ctx.github, labels_to_add)
+            .await?;
+
+        Ok(())
+    }
 }
@@ -34,7 +34,13 @@
 //! ```
 //!
 
-use crate::{config::NoteConfig, github::Event, handlers::Context, interactions::EditIssueBody};
+use crate::{
+    config::NoteConfig,
+    github::Event,
+    handlers::{CommandHandler, Context, Permission, Usage},
+    interactions::EditIssueBody,
+};
+use async_trait::async_trait;
 use itertools::Itertools;
 use parser::command::note::NoteCommand;
 use std::{cmp::Ordering, collections::HashMap};
@@ -114,50 +120,74 @@ impl NoteData {
     }
 }
 
-pub(super) async fn handle_command(
-    ctx: &Context,
-    _config: &NoteConfig,
-    event: &Event,
-    cmd: NoteCommand,
-) -> anyhow::Result<()> {
-    let issue = event.issue().unwrap();
-    let e = EditIssueBody::new(&issue, "SUMMARY");
-
-    let mut current: NoteData = e.current_data().unwrap_or_default();
-
-    let comment_url = String::from(event.html_url().unwrap());
-    let author = event.user().login.to_owned();
-
-    match &cmd {
-        NoteCommand::Summary { title } => {
-            let title = title.to_owned();
-            if let Some(existing_entry) = current.entries_by_url.get_mut(&comment_url) {
-                existing_entry.title = title;
-                log::debug!("Updated existing entry: {:#?}", existing_entry);
-            } else {
-                let new_entry = NoteDataEntry {
-                    title,
-                    comment_url: comment_url.clone(),
-                    author,
-                };
-                log::debug!("New Note Entry: {:#?}", new_entry);
-                current.entries_by_url.insert(comment_url, new_entry);
-                log::debug!("Entries by URL: {:#?}", current.entries_by_url);
+pub(super) struct Handler;
+
+#[async_trait]
+impl CommandHandler for Handler {
+    type Config = NoteConfig;
+    type Command = NoteCommand;
+
+    const NAME: &'static str = "note";
+
+    fn usage(_config: &NoteConfig, bot: &str) -> Vec<Usage> {
+        vec![Usage::new(
+            format!("@{bot} note <title>"),
+            "adds the comment to the summary in the issue description, \
+             and `note remove <title>` removes it.",
+            Permission::Anyone,
+        )]
+    }
+
+    fn permission(_command: &NoteCommand) -> Permission {
+        Permission::Anyone
+    }
+
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        _config: &NoteConfig,
+        event: &Event,
+        cmd: NoteCommand,
+    ) -> anyhow::Result<()> {
+        let issue = event.issue().unwrap();
+        let e = EditIssueBody::new(&issue, "SUMMARY");
+
+        let mut current: NoteData = e.current_data().unwrap_or_default();
+
+        let comment_url = String::from(event.html_url().unwrap());
+        let author = event.user().login.to_owned();
+
+        match &cmd {
+            NoteCommand::Summary { title } => {
+                let title = title.to_owned();
+                if let Some(existing_entry) = current.entries_by_url.get_mut(&comment_url) {
+                    existing_entry.title = title;
+                    log::debug!("Updated existing entry: {:#?}", existing_entry);
+                } else {
+                    let new_entry = NoteDataEntry {
+                        title,
+                        comment_url: comment_url.clone(),
+                        author,
+                    };
+                    log::debug!("New Note Entry: {:#?}", new_entry);
+                    current.entries_by_url.insert(comment_url, new_entry);
+                    log::debug!("Entries by URL: {:#?}", current.entries_by_url);
+                }
             }
-        }
-        NoteCommand::Remove { title } => {
-            if let Some(entry) = current.remove_by_title(title) {
-                log::debug!("SUCCESSFULLY REMOVED ENTRY: {:#?}", entry);
-            } else {
-                log::debug!("UNABLE TO REMOVE ENTRY");
+            NoteCommand::Remove { title } => {
+                if let Some(entry) = current.remove_by_title(title) {
+                    log::debug!("SUCCESSFULLY REMOVED ENTRY: {:#?}", entry);
+                } else {
+                    log::debug!("UNABLE TO REMOVE ENTRY");
+                }
             }
         }
-    }
 
-    let new_markdown = current.to_markdown();
-    log::debug!("New MD: {:#?}", new_markdown);
+        let new_markdown = current.to_markdown();
+        log::debug!("New MD: {:#?}", new_markdown);
 
-    e.apply(&ctx.github, new_markdown, current).await?;
+        e.apply(&ctx.github, new_markdown, current).await?;
 
-    Ok(())
+        Ok(())
+    }
 }
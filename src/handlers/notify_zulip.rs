@@ -1,8 +1,9 @@
 use crate::{
     config::{NotifyZulipConfig, NotifyZulipLabelConfig},
     github::{Issue, IssuesAction, IssuesEvent, Label},
-    handlers::Context,
+    handlers::{Context, IssueHandler},
 };
+use async_trait::async_trait;
 use tracing as log;
 
 pub(super) struct NotifyZulipInput {
@@ -21,31 +22,85 @@ pub(super) enum NotificationType {
     Reopened,
 }
 
-pub(super) async fn parse_input(
-    _ctx: &Context,
-    event: &IssuesEvent,
-    config: Option<&NotifyZulipConfig>,
-) -> Result<Option<Vec<NotifyZulipInput>>, String> {
-    let config = match config {
-        Some(config) => config,
-        None => return Ok(None),
-    };
-
-    match &event.action {
-        IssuesAction::Labeled { label } | IssuesAction::Unlabeled { label } => {
-            let applied_label = label.clone();
-            Ok(config
-                .labels
-                .get(&applied_label.name)
-                .and_then(|label_config| {
-                    parse_label_change_input(event, applied_label, label_config)
-                })
-                .map(|input| vec![input]))
+pub(super) struct Handler;
+
+#[async_trait]
+impl IssueHandler for Handler {
+    type Config = NotifyZulipConfig;
+    type Input = Vec<NotifyZulipInput>;
+
+    const NAME: &'static str = "notify_zulip";
+
+    async fn parse_input(
+        &self,
+        _ctx: &Context,
+        event: &IssuesEvent,
+        config: Option<&NotifyZulipConfig>,
+    ) -> Result<Option<Vec<NotifyZulipInput>>, String> {
+        let config = match config {
+            Some(config) => config,
+            None => return Ok(None),
+        };
+
+        match &event.action {
+            IssuesAction::Labeled { label } | IssuesAction::Unlabeled { label } => {
+                let applied_label = label.clone();
+                Ok(config
+                    .labels
+                    .get(&applied_label.name)
+                    .and_then(|label_config| {
+                        parse_label_change_input(event, applied_label, label_config)
+                    })
+                    .map(|input| vec![input]))
+            }
+            IssuesAction::Closed | IssuesAction::Reopened => {
+                Ok(Some(parse_close_reopen_input(event, config)))
+            }
+            _ => Ok(None),
         }
-        IssuesAction::Closed | IssuesAction::Reopened => {
-            Ok(Some(parse_close_reopen_input(event, config)))
+    }
+
+    async fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &NotifyZulipConfig,
+        event: &IssuesEvent,
+        inputs: Vec<NotifyZulipInput>,
+    ) -> anyhow::Result<()> {
+        for input in inputs {
+            let config = &config.labels[&input.label.name];
+
+            let mut topic = config.topic.clone();
+            topic = topic.replace("{number}", &event.issue.number.to_string());
+            topic = topic.replace("{title}", &event.issue.title);
+            // Truncate to 60 chars (a Zulip limitation)
+            let mut chars = topic.char_indices().skip(59);
+            if let (Some((len, _)), Some(_)) = (chars.next(), chars.next()) {
+                topic.truncate(len);
+                topic.push('…');
+            }
+
+            let mut msg = match input.notification_type {
+                NotificationType::Labeled => config.message_on_add.as_ref().unwrap().clone(),
+                NotificationType::Unlabeled => config.message_on_remove.as_ref().unwrap().clone(),
+                NotificationType::Closed => config.message_on_close.as_ref().unwrap().clone(),
+                NotificationType::Reopened => config.message_on_reopen.as_ref().unwrap().clone(),
+            };
+
+            msg = msg.replace("{number}", &event.issue.number.to_string());
+            msg = msg.replace("{title}", &event.issue.title);
+
+            let zulip_req = crate::zulip::MessageApiRequest {
+                recipient: crate::zulip::Recipient::Stream {
+                    id: config.zulip_stream,
+                    topic: &topic,
+                },
+                content: &msg,
+            };
+            zulip_req.send(&ctx.github).await?;
         }
-        _ => Ok(None),
+
+        Ok(())
     }
 }
 
@@ -130,45 +185,3 @@ fn has_all_required_labels(issue: &Issue, config: &NotifyZulipLabelConfig) -> bo
 
     true
 }
-
-pub(super) async fn handle_input<'a>(
-    ctx: &Context,
-    config: &NotifyZulipConfig,
-    event: &IssuesEvent,
-    inputs: Vec<NotifyZulipInput>,
-) -> anyhow::Result<()> {
-    for input in inputs {
-        let config = &config.labels[&input.label.name];
-
-        let mut topic = config.topic.clone();
-        topic = topic.replace("{number}", &event.issue.number.to_string());
-        topic = topic.replace("{title}", &event.issue.title);
-        // Truncate to 60 chars (a Zulip limitation)
-        let mut chars = topic.char_indices().skip(59);
-        if let (Some((len, _)), Some(_)) = (chars.next(), chars.next()) {
-            topic.truncate(len);
-            topic.push('…');
-        }
-
-        let mut msg = match input.notification_type {
-            NotificationType::Labeled => config.message_on_add.as_ref().unwrap().clone(),
-            NotificationType::Unlabeled => config.message_on_remove.as_ref().unwrap().clone(),
-            NotificationType::Closed => config.message_on_close.as_ref().unwrap().clone(),
-            NotificationType::Reopened => config.message_on_reopen.as_ref().unwrap().clone(),
-        };
-
-        msg = msg.replace("{number}", &event.issue.number.to_string());
-        msg = msg.replace("{title}", &event.issue.title);
-
-        let zulip_req = crate::zulip::MessageApiRequest {
-            recipient: crate::zulip::Recipient::Stream {
-                id: config.zulip_stream,
-                topic: &topic,
-            },
-            content: &msg,
-        };
-        zulip_req.send(&ctx.github.raw()).await?;
-    }
-
-    Ok(())
-}
@@ -1,24 +1,51 @@
 use crate::{
     config::PrioritizeConfig,
     github::{self, Event},
-    handlers::Context,
+    handlers::{CommandHandler, Context, Permission, Usage},
 };
+use async_trait::async_trait;
 use parser::command::prioritize::PrioritizeCommand;
 
-pub(super) async fn handle_command(
-    ctx: &Context,
-    config: &PrioritizeConfig,
-    event: &Event,
-    _: PrioritizeCommand,
-) -> anyhow::Result<()> {
-    let mut labels = vec![];
-    labels.push(github::Label {
-        name: config.label.to_owned(),
-    });
-    event
-        .issue()
-        .unwrap()
-        .add_labels(&ctx.github, labels)
-        .await?;
-    Ok(())
+pub(super) struct Handler;
+
+#[async_trait]
+impl CommandHandler for Handler {
+    type Config = PrioritizeConfig;
+    type Command = PrioritizeCommand;
+
+    const NAME: &'static str = "prioritize";
+
+    fn usage(config: &PrioritizeConfig, bot: &str) -> Vec<Usage> {
+        vec![Usage::new(
+            format!("@{bot} prioritize"),
+            format!(
+                "requests prioritization by adding the `{}` label.",
+                config.label
+            ),
+            Permission::Anyone,
+        )]
+    }
+
+    fn permission(_command: &PrioritizeCommand) -> Permission {
+        Permission::Anyone
+    }
+
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        config: &PrioritizeConfig,
+        event: &Event,
+        _cmd: PrioritizeCommand,
+    ) -> anyhow::Result<()> {
+        let mut labels = vec![];
+        labels.push(github::Label {
+            name: config.label.to_owned(),
+        });
+        event
+            .issue()
+            .unwrap()
+            .add_labels(&ctx.github, labels)
+            .await?;
+        Ok(())
+    }
 }
@@ -11,82 +11,127 @@
 use crate::{
     config::RelabelConfig,
     github::{self, Event, GithubClient},
-    handlers::Context,
+    handlers::{help::code_list, CommandHandler, Context, Permission, Usage},
     interactions::ErrorComment,
 };
-use parser::command::relabel::{LabelDelta, RelabelCommand};
+use async_trait::async_trait;
+use parser::command::{
+    relabel::{LabelDelta, RelabelCommand},
+    Command,
+};
+use std::fmt::Write;
 
-pub(super) async fn handle_command(
-    ctx: &Context,
-    config: &RelabelConfig,
-    event: &Event,
-    input: RelabelCommand,
-) -> anyhow::Result<()> {
-    let mut results = vec![];
-    let mut to_add = vec![];
-    for delta in &input.0 {
-        let name = delta.label().as_str();
-        let err = match check_filter(name, config, is_member(&event.user(), &ctx.github).await) {
-            Ok(CheckFilterResult::Allow) => None,
-            Ok(CheckFilterResult::Deny) => Some(format!(
-                "Label {} can only be set by Rust team members",
-                name
-            )),
-            Ok(CheckFilterResult::DenyUnknown) => Some(format!(
-                "Label {} can only be set by Rust team members;\
-                 we were unable to check if you are a team member.",
-                name
-            )),
-            Err(err) => Some(err),
-        };
-        if let Some(msg) = err {
-            let cmnt = ErrorComment::new(&event.issue().unwrap(), msg);
-            cmnt.post(&ctx.github).await?;
-            return Ok(());
-        }
-        match delta {
-            LabelDelta::Add(label) => {
-                to_add.push(github::Label {
-                    name: label.to_string(),
-                });
-            }
-            LabelDelta::Remove(label) => {
-                results.push((
-                    label,
-                    event.issue().unwrap().remove_label(&ctx.github, &label),
-                ));
+pub(super) struct Handler;
+
+#[async_trait]
+impl CommandHandler for Handler {
+    type Config = RelabelConfig;
+    type Command = RelabelCommand;
+
+    const NAME: &'static str = "relabel";
+
+    fn usage(config: &RelabelConfig, bot: &str) -> Vec<Usage> {
+        let (excluded, allowed): (Vec<_>, Vec<_>) = config
+            .allow_unauthenticated
+            .iter()
+            .map(|p| p.as_str())
+            .partition(|p| p.starts_with('!'));
+        let who = if allowed.is_empty() {
+            "Only team members can change labels.".to_string()
+        } else {
+            let mut who = format!("Anyone can change labels matching {}", code_list(allowed));
+            if !excluded.is_empty() {
+                let excluded = excluded.iter().map(|p| &p[1..]);
+                write!(who, " (except {})", code_list(excluded)).unwrap();
             }
-        }
+            who.push_str("; other labels can only be changed by team members.");
+            who
+        };
+        vec![Usage::new(
+            format!("@{bot} label +<label> -<label>"),
+            format!("adds or removes labels. {who}"),
+            Permission::Described,
+        )]
     }
 
-    if let Err(e) = event
-        .issue()
-        .unwrap()
-        .add_labels(&ctx.github, to_add.clone())
-        .await
-    {
-        tracing::error!(
-            "failed to add {:?} from issue {}: {:?}",
-            to_add,
-            event.issue().unwrap().global_id(),
-            e
-        );
-        return Err(e);
+    fn permission(_command: &RelabelCommand) -> Permission {
+        Permission::Described
     }
 
-    for (label, res) in results {
-        if let Err(e) = res.await {
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        config: &RelabelConfig,
+        event: &Event,
+        input: RelabelCommand,
+    ) -> anyhow::Result<()> {
+        let mut results = vec![];
+        let mut to_add = vec![];
+        for delta in &input.0 {
+            let name = delta.label().as_str();
+            let err = match check_filter(name, config, is_member(&event.user(), &ctx.github).await)
+            {
+                Ok(CheckFilterResult::Allow) => None,
+                Ok(CheckFilterResult::Deny) => Some(format!(
+                    "Label {} can only be set by Rust team members",
+                    name
+                )),
+                Ok(CheckFilterResult::DenyUnknown) => Some(format!(
+                    "Label {} can only be set by Rust team members;\
+                     we were unable to check if you are a team member.",
+                    name
+                )),
+                Err(err) => Some(err),
+            };
+            if let Some(msg) = err {
+                let cmnt = ErrorComment::new(&event.issue().unwrap(), msg);
+                cmnt.post(&ctx.github).await?;
+                return Ok(());
+            }
+            match delta {
+                LabelDelta::Add(label) => {
+                    to_add.push(github::Label {
+                        name: label.to_string(),
+                    });
+                }
+                LabelDelta::Remove(label) => {
+                    results.push((
+                        label,
+                        event.issue().unwrap().remove_label(&ctx.github, &label),
+                    ));
+                }
+            }
+        }
+
+        if let Err(e) = event
+            .issue()
+            .unwrap()
+            .add_labels(&ctx.github, to_add.clone())
+            .await
+        {
             tracing::error!(
-                "failed to remove {:?} from issue {}: {:?}",
-                label,
+                "failed to add {:?} from issue {}: {:?}",
+                to_add,
                 event.issue().unwrap().global_id(),
                 e
             );
             return Err(e);
         }
-    }
 
-    Ok(())
+        for (label, res) in results {
+            if let Err(e) = res.await {
+                tracing::error!(
+                    "failed to remove {:?} from issue {}: {:?}",
+                    label,
+                    event.issue().unwrap().global_id(),
+                    e
+                );
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -1,57 +1,70 @@
 use crate::config::ReviewRequestedConfig;
 use crate::github::{IssuesAction, IssuesEvent, Label};
-use crate::handlers::Context;
+use crate::handlers::{Context, IssueHandler};
+use async_trait::async_trait;
 
 pub(crate) struct ReviewRequestedInput {}
 
-pub(crate) async fn parse_input(
-    _ctx: &Context,
-    event: &IssuesEvent,
-    config: Option<&ReviewRequestedConfig>,
-) -> Result<Option<ReviewRequestedInput>, String> {
-    // PR author requests a review from one of the assignees
+pub(super) struct Handler;
 
-    if config.is_none() {
-        return Ok(None);
-    }
+#[async_trait]
+impl IssueHandler for Handler {
+    type Config = ReviewRequestedConfig;
+    type Input = ReviewRequestedInput;
 
-    let IssuesAction::ReviewRequested { requested_reviewer } = &event.action else {
-        return Ok(None);
-    };
+    const NAME: &'static str = "review_requested";
 
-    if event.sender != event.issue.user {
-        return Ok(None);
-    }
+    async fn parse_input(
+        &self,
+        _ctx: &Context,
+        event: &IssuesEvent,
+        config: Option<&ReviewRequestedConfig>,
+    ) -> Result<Option<ReviewRequestedInput>, String> {
+        // PR author requests a review from one of the assignees
 
-    if !event.issue.assignees.contains(requested_reviewer) {
-        return Ok(None);
-    }
+        if config.is_none() {
+            return Ok(None);
+        }
 
-    Ok(Some(ReviewRequestedInput {}))
-}
+        let IssuesAction::ReviewRequested { requested_reviewer } = &event.action else {
+            return Ok(None);
+        };
+
+        if event.sender != event.issue.user {
+            return Ok(None);
+        }
 
-pub(crate) async fn handle_input(
-    ctx: &Context,
-    config: &ReviewRequestedConfig,
-    event: &IssuesEvent,
-    ReviewRequestedInput {}: ReviewRequestedInput,
-) -> anyhow::Result<()> {
-    event
-        .issue
-        .add_labels(
-            &ctx.github,
-            config
-                .add_labels
-                .iter()
-                .cloned()
-                .map(|name| Label { name })
-                .collect(),
-        )
-        .await?;
-
-    for label in &config.remove_labels {
-        event.issue.remove_label(&ctx.github, label).await?;
+        if !event.issue.assignees.contains(requested_reviewer) {
+            return Ok(None);
+        }
+
+        Ok(Some(ReviewRequestedInput {}))
     }
 
-    Ok(())
+    async fn handle_input(
+        &self,
+        ctx: &Context,
+        config: &ReviewRequestedConfig,
+        event: &IssuesEvent,
+        ReviewRequestedInput {}: ReviewRequestedInput,
+    ) -> anyhow::Result<()> {
+        event
+            .issue
+            .add_labels(
+                &ctx.github,
+                config
+                    .add_labels
+                    .iter()
+                    .cloned()
+                    .map(|name| Label { name })
+                    .collect(),
+            )
+            .await?;
+
+        for label in &config.remove_labels {
+            event.issue.remove_label(&ctx.github, label).await?;
+        }
+
+        Ok(())
+    }
 }
@@ -5,53 +5,89 @@
 use crate::{
     config::ShortcutConfig,
     github::{Event, Label},
-    handlers::Context,
+    handlers::{CommandHandler, Context, Permission, Usage},
     interactions::ErrorComment,
 };
+use async_trait::async_trait;
 use parser::command::shortcut::ShortcutCommand;
 
-pub(super) async fn handle_command(
-    ctx: &Context,
-    _config: &ShortcutConfig,
-    event: &Event,
-    input: ShortcutCommand,
-) -> anyhow::Result<()> {
-    let issue = event.issue().unwrap();
-    // NOTE: if shortcuts available to issues are created, they need to be allowed here
-    if !issue.is_pr() {
-        let msg = format!("The \"{:?}\" shortcut only works on pull requests.", input);
-        let cmnt = ErrorComment::new(&issue, msg);
-        cmnt.post(&ctx.github).await?;
-        return Ok(());
+pub(super) struct Handler;
+
+#[async_trait]
+impl CommandHandler for Handler {
+    type Config = ShortcutConfig;
+    type Command = ShortcutCommand;
+
+    const NAME: &'static str = "shortcut";
+
+    fn usage(_config: &ShortcutConfig, bot: &str) -> Vec<Usage> {
+        vec![
+            Usage::new(
+                format!("@{bot} ready"),
+                "marks the pull request as waiting on review.",
+                Permission::Anyone,
+            ),
+            Usage::new(
+                format!("@{bot} author"),
+                "marks the pull request as waiting on the author.",
+                Permission::Anyone,
+            ),
+            Usage::new(
+                format!("@{bot} blocked"),
+                "marks the pull request as blocked.",
+                Permission::Anyone,
+            ),
+        ]
     }
 
-    let issue_labels = issue.labels();
-    let waiting_on_review = "S-waiting-on-review";
-    let waiting_on_author = "S-waiting-on-author";
-    let blocked = "S-blocked";
-    let status_labels = [waiting_on_review, waiting_on_author, blocked];
-
-    let add = match input {
-        ShortcutCommand::Ready => waiting_on_review,
-        ShortcutCommand::Author => waiting_on_author,
-        ShortcutCommand::Blocked => blocked,
-    };
-
-    if !issue_labels.iter().any(|l| l.name == add) {
-        for remove in status_labels {
-            if remove != add {
-                issue.remove_label(&ctx.github, remove).await?;
+    fn permission(_command: &ShortcutCommand) -> Permission {
+        Permission::Anyone
+    }
+
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        _config: &ShortcutConfig,
+        event: &Event,
+        input: ShortcutCommand,
+    ) -> anyhow::Result<()> {
+        let issue = event.issue().unwrap();
+        // NOTE: if shortcuts available to issues are created, they need to be allowed here
+        if !issue.is_pr() {
+            let msg = format!("The \"{:?}\" shortcut only works on pull requests.", input);
+            let cmnt = ErrorComment::new(&issue, msg);
+            cmnt.post(&ctx.github).await?;
+            return Ok(());
+        }
+
+        let issue_labels = issue.labels();
+        let waiting_on_review = "S-waiting-on-review";
+        let waiting_on_author = "S-waiting-on-author";
+        let blocked = "S-blocked";
+        let status_labels = [waiting_on_review, waiting_on_author, blocked];
+
+        let add = match input {
+            ShortcutCommand::Ready => waiting_on_review,
+            ShortcutCommand::Author => waiting_on_author,
+            ShortcutCommand::Blocked => blocked,
+        };
+
+        if !issue_labels.iter().any(|l| l.name == add) {
+            for remove in status_labels {
+                if remove != add {
+                    issue.remove_label(&ctx.github, remove).await?;
+                }
             }
+            issue
+                .add_labels(
+                    &ctx.github,
+                    vec![Label {
+                        name: add.to_owned(),
+                    }],
+                )
+                .await?;
         }
-        issue
-            .add_labels(
-                &ctx.github,
-                vec![Label {
-                    name: add.to_owned(),
-                }],
-            )
-            .await?;
-    }
 
-    Ok(())
+        Ok(())
+    }
 }
@@ -0,0 +1,120 @@
+//! For pull requests that change `triagebot.toml`, checks that the new file
+//! is valid, so mistakes are caught before they are merged rather than
+//! showing up as errors on unrelated issues afterwards.
+//!
+//! Problems are reported in a comment, which is only posted again if the
+//! problems change. Once they are fixed, a comment says so. Nothing is posted
+//! if a later push reverts the change to the file entirely.
+
+use crate::{
+    config::{self, ConfigurationError},
+    db::issue_data::IssueData,
+    github::{Event, IssuesAction, IssuesEvent},
+    handlers::Context,
+};
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use tracing as log;
+
+const CONFIG_FILE_NAME: &str = "triagebot.toml";
+const VALIDATE_CONFIG_KEY: &str = "validate_config";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ValidateConfigState {
+    /// The problems mentioned in the last comment posted by triagebot.
+    reported_problems: Vec<String>,
+}
+
+pub(super) async fn handle(ctx: &Context, event: &Event) -> anyhow::Result<()> {
+    let Event::Issue(event) = event else {
+        return Ok(());
+    };
+    if !event.issue.is_pr()
+        || !matches!(
+            event.action,
+            IssuesAction::Opened | IssuesAction::Reopened | IssuesAction::Synchronize
+        )
+    {
+        return Ok(());
+    }
+
+    let files = event.issue.files(&ctx.github).await?;
+    if !files.iter().any(|f| f.filename == CONFIG_FILE_NAME) {
+        return Ok(());
+    }
+    let problems = check_config(ctx, event).await?;
+
+    let mut client = ctx.db.get().await;
+    let mut state: IssueData<'_, ValidateConfigState> =
+        IssueData::load(&mut client, &event.issue, VALIDATE_CONFIG_KEY).await?;
+    if problems == state.data.reported_problems {
+        return Ok(());
+    }
+
+    let message = if problems.is_empty() {
+        format!("The problems with `{CONFIG_FILE_NAME}` have been fixed.")
+    } else {
+        let mut message =
+            format!("The `{CONFIG_FILE_NAME}` in this pull request has some problems:\n\n");
+        for problem in &problems {
+            writeln!(message, "- {problem}").unwrap();
+        }
+        message
+    };
+    log::info!(
+        "reporting {} config problems on {}",
+        problems.len(),
+        event.issue.global_id()
+    );
+    event
+        .issue
+        .post_comment(&ctx.github, &message)
+        .await
+        .context("failed to post validate_config comment")?;
+    state.data.reported_problems = problems;
+    state.save().await?;
+    Ok(())
+}
+
+/// Fetches the config from the head of the pull request, and returns the
+/// problems with it.
+async fn check_config(ctx: &Context, event: &IssuesEvent) -> anyhow::Result<Vec<String>> {
+    let head = event
+        .issue
+        .head
+        .as_ref()
+        .context("pull request has no head")?;
+    let Some(contents) = ctx
+        .github
+        .raw_file(&head.repo.full_name, &head.sha, CONFIG_FILE_NAME)
+        .await?
+    else {
+        // The file was deleted, which disables triagebot. That is presumably
+        // intentional.
+        return Ok(Vec::new());
+    };
+    let (config, unknown_keys) =
+        match config::parse(&ctx.github, &event.repository, &contents).await {
+            Ok(parsed) => parsed,
+            Err(ConfigurationError::Toml(e)) => {
+                return Ok(vec![format!("The file could not be parsed: {e}")])
+            }
+            // The organization's configuration is inherited, so its problems
+            // break this one too.
+            Err(e @ ConfigurationError::OrgToml(_)) => return Ok(vec![e.to_string()]),
+            // Fetching the organization's configuration failed; the handler
+            // runs again when the event is retried. `parse` never returns
+            // `Missing`.
+            Err(e @ (ConfigurationError::Http(_) | ConfigurationError::Missing)) => {
+                return Err(e.into())
+            }
+        };
+    let teams = crate::team_data::teams(&ctx.github).await?;
+    let mut problems: Vec<_> = unknown_keys
+        .iter()
+        .map(|key| format!("Unknown key `{key}` is ignored"))
+        .collect();
+    problems.extend(config.validate(&teams));
+    Ok(problems)
+}
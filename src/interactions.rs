@@ -1,4 +1,5 @@
 use crate::github::{GithubClient, Issue};
+use once_cell::sync::Lazy;
 use std::fmt::Write;
 
 pub struct ErrorComment<'a> {
@@ -21,15 +22,35 @@ impl<'a> ErrorComment<'a> {
         let mut body = String::new();
         writeln!(body, "**Error**: {}", self.message)?;
         writeln!(body)?;
-        writeln!(
+        write!(
             body,
             "Please file an issue on GitHub at [triagebot](https://github.com/rust-lang/triagebot) if there's \
-            a problem with this bot, or reach out on [#t-infra](https://rust-lang.zulipchat.com/#narrow/stream/242791-t-infra) on Zulip."
+            a problem with this bot"
         )?;
+        if let Some(stream) = help_stream() {
+            let name = stream.split_once('-').map_or(stream, |(_, name)| name);
+            write!(
+                body,
+                ", or reach out on [#{name}]({}/#narrow/stream/{stream}) on Zulip",
+                crate::zulip::zulip_url()
+            )?;
+        }
+        writeln!(body, ".")?;
         self.issue.post_comment(client, &body).await
     }
 }
 
+/// The Zulip stream which error comments point to, as `<id>-<name>`.
+///
+/// This can be changed with `ZULIP_HELP_STREAM`; setting it to an empty
+/// string leaves out the Zulip link.
+fn help_stream() -> Option<&'static str> {
+    static STREAM: Lazy<String> = Lazy::new(|| {
+        std::env::var("ZULIP_HELP_STREAM").unwrap_or_else(|_| "242791-t-infra".to_string())
+    });
+    Some(STREAM.as_str()).filter(|stream| !stream.is_empty())
+}
+
 pub struct PingComment<'a> {
     issue: &'a Issue,
     users: &'a [&'a str],
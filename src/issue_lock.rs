@@ -0,0 +1,242 @@
+//! Serializes the processing of webhook events for the same issue.
+//!
+//! Handlers often read the state of an issue (its labels, assignees, or the
+//! sections of its description managed by `EditIssueBody`) and write it back,
+//! so two events for the same issue processed at once can undo each other's
+//! changes. GitHub commonly sends several events for an issue within
+//! milliseconds, e.g. `opened` and `labeled` for a new pull request.
+//!
+//! Events about the same issue run one after the other, in the order in
+//! which they reserved their turn with [`reserve`], while events about
+//! different issues still run in parallel. The webhook endpoint reserves a
+//! turn as soon as it has read an event, before anything else is awaited, so
+//! events run in the order they were received.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+/// An issue or pull request.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct IssueKey {
+    /// The repository, as `owner/name`.
+    pub repo: String,
+    pub number: u64,
+}
+
+impl IssueKey {
+    /// Finds which issue a webhook payload is about, if any.
+    pub fn from_payload(payload: &str) -> Option<IssueKey> {
+        let payload: serde_json::Value = serde_json::from_str(payload).ok()?;
+        let repo = payload["repository"]["full_name"].as_str()?;
+        let number = payload["issue"]["number"]
+            .as_u64()
+            .or_else(|| payload["pull_request"]["number"].as_u64())?;
+        Some(IssueKey {
+            repo: repo.to_string(),
+            number,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Queues {
+    next_id: u64,
+    /// The last turn reserved for each issue which is being processed or
+    /// waited for: its ID, and what the next turn waits for.
+    last: HashMap<IssueKey, (u64, oneshot::Receiver<()>)>,
+}
+
+static QUEUES: Lazy<Mutex<Queues>> = Lazy::new(Default::default);
+
+/// A reserved turn to process an event about an issue.
+pub struct IssueTicket {
+    key: IssueKey,
+    id: u64,
+    /// Resolves once the previous turn is over.
+    previous: Option<oneshot::Receiver<()>>,
+    /// Dropped once this turn is over.
+    done: Option<oneshot::Sender<()>>,
+}
+
+/// Allows processing events about an issue until it is dropped.
+pub struct IssueGuard {
+    key: IssueKey,
+    id: u64,
+    _done: oneshot::Sender<()>,
+}
+
+impl Drop for IssueGuard {
+    fn drop(&mut self) {
+        let mut queues = QUEUES.lock().unwrap();
+        // Forget the issue if nothing else is waiting for it.
+        if queues.last.get(&self.key).map(|(id, _)| *id) == Some(self.id) {
+            queues.last.remove(&self.key);
+        }
+    }
+}
+
+/// Reserves the next turn to process events about the issue.
+pub fn reserve(key: IssueKey) -> IssueTicket {
+    let mut queues = QUEUES.lock().unwrap();
+    let id = queues.next_id;
+    queues.next_id += 1;
+    let (done, next) = oneshot::channel();
+    let previous = queues
+        .last
+        .insert(key.clone(), (id, next))
+        .map(|(_, previous)| previous);
+    IssueTicket {
+        key,
+        id,
+        previous,
+        done: Some(done),
+    }
+}
+
+impl IssueTicket {
+    /// Waits until the turns reserved earlier are over.
+    pub async fn wait(mut self) -> IssueGuard {
+        if let Some(previous) = &mut self.previous {
+            // The sender is never used; it is dropped at the end of the turn.
+            let _ = previous.await;
+        }
+        self.previous = None;
+        IssueGuard {
+            key: self.key.clone(),
+            id: self.id,
+            _done: self.done.take().unwrap(),
+        }
+    }
+}
+
+impl Drop for IssueTicket {
+    fn drop(&mut self) {
+        let Some(done) = self.done.take() else {
+            return;
+        };
+        // The turn was given up without waiting for it, but the later ones
+        // must still wait for the earlier ones.
+        let guard = IssueGuard {
+            key: self.key.clone(),
+            id: self.id,
+            _done: done,
+        };
+        match (self.previous.take(), tokio::runtime::Handle::try_current()) {
+            (Some(previous), Ok(runtime)) => {
+                runtime.spawn(async move {
+                    let _ = previous.await;
+                    drop(guard);
+                });
+            }
+            _ => drop(guard),
+        }
+    }
+}
+
+/// Waits until events about the issue which reserved their turn earlier
+/// have been processed.
+pub async fn lock(key: IssueKey) -> IssueGuard {
+    reserve(key).wait().await
+}
+
+/// Reserves a turn for the issue a webhook payload is about, if any.
+pub fn reserve_payload(payload: &str) -> Option<IssueTicket> {
+    IssueKey::from_payload(payload).map(reserve)
+}
+
+/// Locks the issue a webhook payload is about, if any.
+pub async fn lock_payload(payload: &str) -> Option<IssueGuard> {
+    match reserve_payload(payload) {
+        Some(ticket) => Some(ticket.wait().await),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    fn key(repo: &str, number: u64) -> IssueKey {
+        IssueKey {
+            repo: repo.to_string(),
+            number,
+        }
+    }
+
+    #[test]
+    fn from_payload() {
+        let issue = r#"{"action": "labeled", "issue": {"number": 1},
+            "repository": {"full_name": "rust-lang/rust"}}"#;
+        assert_eq!(
+            IssueKey::from_payload(issue),
+            Some(key("rust-lang/rust", 1))
+        );
+        let pull_request = r#"{"action": "opened", "number": 2, "pull_request": {"number": 2},
+            "repository": {"full_name": "rust-lang/rust"}}"#;
+        assert_eq!(
+            IssueKey::from_payload(pull_request),
+            Some(key("rust-lang/rust", 2))
+        );
+        let push = r#"{"ref": "refs/heads/master",
+            "repository": {"full_name": "rust-lang/rust"}}"#;
+        assert_eq!(IssueKey::from_payload(push), None);
+    }
+
+    #[tokio::test]
+    async fn same_issue_waits() {
+        let first = lock(key("a/b", 1)).await;
+        let mut second = lock(key("a/b", 1)).boxed();
+        assert!((&mut second).now_or_never().is_none());
+        // Other issues are not affected.
+        let other = lock(key("a/b", 2)).now_or_never();
+        assert!(other.is_some());
+        drop(other);
+
+        drop(first);
+        let second = second.now_or_never().expect("first guard was dropped");
+        drop(second);
+        assert!(!QUEUES.lock().unwrap().last.contains_key(&key("a/b", 1)));
+        assert!(!QUEUES.lock().unwrap().last.contains_key(&key("a/b", 2)));
+    }
+
+    #[tokio::test]
+    async fn reservation_order() {
+        // Two events received back to back, whose tasks start in the other
+        // order.
+        let first = reserve(key("a/c", 1));
+        let second = reserve(key("a/c", 1));
+        let order = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let record = |n, ticket: IssueTicket| {
+            let order = order.clone();
+            tokio::spawn(async move {
+                let _guard = ticket.wait().await;
+                tokio::task::yield_now().await;
+                order.lock().unwrap().push(n);
+            })
+        };
+        let second = record(2, second);
+        tokio::task::yield_now().await;
+        let first = record(1, first);
+        second.await.unwrap();
+        first.await.unwrap();
+        assert_eq!(*order.lock().unwrap(), [1, 2]);
+        assert!(!QUEUES.lock().unwrap().last.contains_key(&key("a/c", 1)));
+    }
+
+    #[tokio::test]
+    async fn given_up_turn_keeps_order() {
+        let first = lock(key("a/d", 1)).await;
+        let skipped = reserve(key("a/d", 1));
+        let mut third = reserve(key("a/d", 1)).wait().boxed();
+        drop(skipped);
+        tokio::task::yield_now().await;
+        assert!((&mut third).now_or_never().is_none());
+        drop(first);
+        let third = third.await;
+        drop(third);
+        assert!(!QUEUES.lock().unwrap().last.contains_key(&key("a/d", 1)));
+    }
+}
@@ -16,16 +16,19 @@ pub mod agenda;
 mod changelogs;
 pub mod config;
 pub mod db;
+pub mod dry_run;
 pub mod github;
 pub mod handlers;
 pub mod http_client;
 pub mod interactions;
+pub mod issue_lock;
 pub mod jobs;
+pub mod metrics;
 pub mod notification_listing;
 pub mod payload;
 pub mod rfcbot;
 pub mod team;
-mod team_data;
+pub mod team_data;
 pub mod triage;
 pub mod zulip;
 
@@ -114,14 +117,53 @@ impl fmt::Display for EventName {
 }
 
 #[derive(Debug)]
-pub struct WebhookError(anyhow::Error);
+pub struct WebhookError {
+    error: anyhow::Error,
+    permanent: bool,
+}
+
+impl WebhookError {
+    /// An error which processing the event again would run into as well,
+    /// e.g. because its payload can't be deserialized.
+    fn permanent(error: anyhow::Error) -> WebhookError {
+        WebhookError {
+            error,
+            permanent: true,
+        }
+    }
+
+    /// Whether the event should not be processed again.
+    pub fn is_permanent(&self) -> bool {
+        self.permanent
+    }
+
+    pub fn into_inner(self) -> anyhow::Error {
+        self.error
+    }
+}
 
 impl From<anyhow::Error> for WebhookError {
-    fn from(e: anyhow::Error) -> WebhookError {
-        WebhookError(e)
+    fn from(error: anyhow::Error) -> WebhookError {
+        WebhookError {
+            error,
+            permanent: false,
+        }
     }
 }
 
+/// The state of a webhook delivery across processing attempts.
+#[derive(Debug, Default)]
+pub struct WebhookAttempt {
+    /// The handlers to run, or `None` to run all of them.
+    ///
+    /// Once the handlers have run, this is set to those which failed with an
+    /// internal error, so that a retry doesn't repeat what the others have
+    /// already done, e.g. post a comment.
+    pub handlers: Option<Vec<String>>,
+    /// A description of every error reported by a handler.
+    pub handler_errors: Vec<String>,
+}
+
 pub fn deserialize_payload<T: serde::de::DeserializeOwned>(v: &str) -> anyhow::Result<T> {
     let mut deserializer = serde_json::Deserializer::from_str(&v);
     let res: Result<T, _> = serde_path_to_error::deserialize(&mut deserializer);
@@ -134,19 +176,28 @@ pub fn deserialize_payload<T: serde::de::DeserializeOwned>(v: &str) -> anyhow::R
     }
 }
 
+/// Processes a webhook event, running the handlers selected by `attempt`.
+///
+/// Errors meant for the user are posted as a comment, while internal errors
+/// cause this to return an error. Both are recorded in `attempt`.
 pub async fn webhook(
     event: EventName,
     payload: String,
     ctx: &handlers::Context,
+    attempt: &mut WebhookAttempt,
 ) -> Result<bool, WebhookError> {
+    let event_name = event.to_string();
+    metrics::WEBHOOK_EVENTS
+        .with_label_values(&[&event_name])
+        .inc();
     let event = match event {
         EventName::PullRequestReview => {
             let mut payload = deserialize_payload::<github::PullRequestReviewEvent>(&payload)
                 .context("PullRequestReview failed to deserialize")
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::permanent)?;
 
             log::info!("handling pull request review comment {:?}", payload);
-            payload.pull_request.pull_request = Some(PullRequestDetails {});
+            payload.pull_request.pull_request = Some(PullRequestDetails::default());
 
             // Treat pull request review comments exactly like pull request
             // review comments.
@@ -169,9 +220,9 @@ pub async fn webhook(
         EventName::PullRequestReviewComment => {
             let mut payload = deserialize_payload::<github::PullRequestReviewComment>(&payload)
                 .context("PullRequestReview(Comment) failed to deserialize")
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::permanent)?;
 
-            payload.issue.pull_request = Some(PullRequestDetails {});
+            payload.issue.pull_request = Some(PullRequestDetails::default());
 
             log::info!("handling pull request review comment {:?}", payload);
 
@@ -188,7 +239,7 @@ pub async fn webhook(
         EventName::IssueComment => {
             let payload = deserialize_payload::<github::IssueCommentEvent>(&payload)
                 .context("IssueCommentEvent failed to deserialize")
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::permanent)?;
 
             log::info!("handling issue comment {:?}", payload);
 
@@ -197,10 +248,10 @@ pub async fn webhook(
         EventName::Issue | EventName::PullRequest => {
             let mut payload = deserialize_payload::<github::IssuesEvent>(&payload)
                 .context(format!("{:?} failed to deserialize", event))
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::permanent)?;
 
             if matches!(event, EventName::PullRequest) {
-                payload.issue.pull_request = Some(PullRequestDetails {});
+                payload.issue.pull_request = Some(PullRequestDetails::default());
             }
 
             log::info!("handling issue event {:?}", payload);
@@ -210,7 +261,7 @@ pub async fn webhook(
         EventName::Push => {
             let payload = deserialize_payload::<github::PushEvent>(&payload)
                 .with_context(|| format!("{:?} failed to deserialize", event))
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::permanent)?;
 
             log::info!("handling push event {:?}", payload);
 
@@ -219,7 +270,7 @@ pub async fn webhook(
         EventName::Create => {
             let payload = deserialize_payload::<github::CreateEvent>(&payload)
                 .with_context(|| format!("{:?} failed to deserialize", event))
-                .map_err(anyhow::Error::from)?;
+                .map_err(WebhookError::permanent)?;
 
             log::info!("handling create event {:?}", payload);
 
@@ -230,12 +281,47 @@ pub async fn webhook(
             return Ok(false);
         }
     };
-    let errors = handlers::handle(&ctx, &event).await;
-    let mut other_error = false;
+    // When running as a GitHub App, act as the installation for the
+    // event's repository.
+    let repo_ctx = ctx.for_repository(&event.repo().full_name).await?;
+    let ctx = repo_ctx.as_ref().unwrap_or(ctx);
+    if let github::Event::Push(push) = &event {
+        config::evict_on_push(event.repo(), push);
+    }
+    let config = config::get_for_event(&ctx.github, &event).await;
+    match &config {
+        Err(e @ config::ConfigurationError::Http(_)) => {
+            // Handlers can't tell whether they are enabled, so none of them
+            // run until the configuration can be loaded.
+            return Err(anyhow::Error::from(e.clone())
+                .context("failed to load the configuration")
+                .into());
+        }
+        Err(e) => log::warn!("configuration error {}: {e}", event.repo().full_name),
+        Ok(_) => {}
+    }
+    // Everything done for a repository in dry-run mode, including posting
+    // the error comment below, goes through this context.
+    let dry_run_ctx;
+    let ctx = match &config {
+        Ok(config) if config.dry_run && !ctx.github.is_dry_run() => {
+            dry_run_ctx = ctx.dry_run();
+            &dry_run_ctx
+        }
+        _ => ctx,
+    };
+    let timer = metrics::HANDLE_DURATION
+        .with_label_values(&[&event_name])
+        .start_timer();
+    let outcome = handlers::handle(ctx, &event, &config, attempt.handlers.as_deref()).await;
+    timer.observe_duration();
+    attempt.handlers = Some(outcome.failed.iter().map(|name| name.to_string()).collect());
+    let mut internal_errors = Vec::new();
     let mut message = String::new();
-    for err in errors {
+    for err in outcome.errors {
         match err {
             HandlerError::Message(msg) => {
+                attempt.handler_errors.push(msg.clone());
                 if !message.is_empty() {
                     message.push_str("\n\n");
                 }
@@ -243,7 +329,8 @@ pub async fn webhook(
             }
             HandlerError::Other(err) => {
                 log::error!("handling event failed: {:?}", err);
-                other_error = true;
+                attempt.handler_errors.push(format!("{:?}", err));
+                internal_errors.push(format!("{:?}", err));
             }
         }
     }
@@ -253,10 +340,8 @@ pub async fn webhook(
             cmnt.post(&ctx.github).await?;
         }
     }
-    if other_error {
-        Err(WebhookError(anyhow::anyhow!(
-            "handling failed, error logged",
-        )))
+    if !internal_errors.is_empty() {
+        Err(anyhow::anyhow!("handling failed:\n\n{}", internal_errors.join("\n\n")).into())
     } else {
         Ok(true)
     }
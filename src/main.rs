@@ -11,8 +11,11 @@ use tokio::{task, time};
 use tower::{Service, ServiceExt};
 use tracing as log;
 use tracing::Instrument;
+use triagebot::db::WEBHOOK_RETRY_CADENCE_IN_SECS;
 use triagebot::jobs::{jobs, JOB_PROCESSING_CADENCE_IN_SECS, JOB_SCHEDULING_CADENCE_IN_SECS};
-use triagebot::{db, github, handlers::Context, notification_listing, payload, EventName};
+use triagebot::{
+    db, github, handlers::Context, issue_lock, notification_listing, payload, EventName,
+};
 
 async fn handle_agenda_request(req: String) -> anyhow::Result<String> {
     if req == "/agenda/lang/triage" {
@@ -88,6 +91,28 @@ async fn serve_req(
             .body(Body::from("Triagebot is awaiting triage."))
             .unwrap());
     }
+    if req.uri.path() == "/metrics" {
+        return Ok(match triagebot::metrics::render() {
+            Ok(metrics) => Response::builder()
+                .status(StatusCode::OK)
+                .header("Content-Type", "text/plain; version=0.0.4")
+                .body(Body::from(metrics))
+                .unwrap(),
+            Err(e) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(format!("{:?}", e)))
+                .unwrap(),
+        });
+    }
+    if req.uri.path() == "/triagebot.schema.json" {
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::to_string_pretty(&triagebot::config::schema()).unwrap(),
+            ))
+            .unwrap());
+    }
     if req.uri.path() == "/bors-commit-list" {
         let res = db::rustc_commits::get_commits_with_artifacts(&*ctx.db.get().await).await;
         let res = match res {
@@ -151,6 +176,90 @@ async fn serve_req(
             .body(Body::from(triagebot::zulip::respond(&ctx, req).await))
             .unwrap());
     }
+    if req.uri.path() == "/admin/replay" {
+        if req.method != hyper::Method::POST {
+            return Ok(Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, "POST")
+                .body(Body::empty())
+                .unwrap());
+        }
+        if !is_admin_request(&req.headers) {
+            return Ok(Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(Body::empty())
+                .unwrap());
+        }
+        let query = req.uri.query().unwrap_or_default();
+        let mut delivery_id = None;
+        let mut dry_run = false;
+        for (k, v) in url::form_urlencoded::parse(query.as_bytes()) {
+            match &*k {
+                "delivery" => delivery_id = Some(v.into_owned()),
+                "dry_run" => dry_run = v != "0",
+                _ => {}
+            }
+        }
+        let Some(delivery_id) = delivery_id else {
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(
+                    "Please provide `?delivery=<delivery-id>` query param on URL.",
+                ))
+                .unwrap());
+        };
+
+        let res = if dry_run {
+            let ctx = ctx.dry_run();
+            db::replay_webhook(&ctx, &*ctx.db.get().await, &delivery_id).await
+        } else {
+            db::replay_webhook(&ctx, &*ctx.db.get().await, &delivery_id).await
+        };
+        return Ok(match res {
+            Ok(report) => Response::new(Body::from(report)),
+            Err(e) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(format!("{:?}", e)))
+                .unwrap(),
+        });
+    }
+    if req.uri.path() == "/admin/dry-run" {
+        if !is_admin_request(&req.headers) {
+            return Ok(Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(Body::empty())
+                .unwrap());
+        }
+        return Ok(Response::builder()
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::to_string(&triagebot::dry_run::actions()).unwrap(),
+            ))
+            .unwrap());
+    }
+    if req.uri.path() == "/admin/reload-config" {
+        if req.method != hyper::Method::POST {
+            return Ok(Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, "POST")
+                .body(Body::empty())
+                .unwrap());
+        }
+        if !is_admin_request(&req.headers) {
+            return Ok(Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(Body::empty())
+                .unwrap());
+        }
+        let query = req.uri.query().unwrap_or_default();
+        let scope = url::form_urlencoded::parse(query.as_bytes())
+            .find(|(k, _)| k == "repo")
+            .map(|(_, v)| v.into_owned());
+        let evicted = triagebot::config::clear_cache(scope.as_deref());
+        return Ok(Response::new(Body::from(format!(
+            "evicted {evicted} cached configurations\n"
+        ))));
+    }
     if req.uri.path() != "/github-hook" {
         return Ok(Response::builder()
             .status(StatusCode::NOT_FOUND)
@@ -185,20 +294,53 @@ async fn serve_req(
             .unwrap());
     };
     log::debug!("event={}", event);
-    let signature = if let Some(sig) = req.headers.get("X-Hub-Signature") {
+    if matches!(event, EventName::Other) {
+        return Ok(Response::new(Body::from("ignored request")));
+    }
+    let delivery_id = match req
+        .headers
+        .get("X-GitHub-Delivery")
+        .map(|id| id.to_str().ok())
+    {
+        Some(Some(id)) => id.to_string(),
+        Some(None) => {
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from("X-GitHub-Delivery header must be UTF-8 encoded"))
+                .unwrap());
+        }
+        None => {
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from("X-GitHub-Delivery header must be set"))
+                .unwrap());
+        }
+    };
+    log::debug!("delivery={}", delivery_id);
+    // Prefer the SHA-256 signature, only falling back to the legacy SHA-1
+    // header if that has been explicitly enabled.
+    let signature_header =
+        if req.headers.contains_key(payload::SIGNATURE_256_HEADER) || !payload::sha1_allowed() {
+            payload::SIGNATURE_256_HEADER
+        } else {
+            payload::SIGNATURE_SHA1_HEADER
+        };
+    let signature = if let Some(sig) = req.headers.get(signature_header) {
         match sig.to_str().ok() {
             Some(v) => v,
             None => {
                 return Ok(Response::builder()
                     .status(StatusCode::BAD_REQUEST)
-                    .body(Body::from("X-Hub-Signature header must be UTF-8 encoded"))
+                    .body(Body::from(format!(
+                        "{signature_header} header must be UTF-8 encoded"
+                    )))
                     .unwrap());
             }
         }
     } else {
         return Ok(Response::builder()
             .status(StatusCode::BAD_REQUEST)
-            .body(Body::from("X-Hub-Signature header must be set"))
+            .body(Body::from(format!("{signature_header} header must be set")))
             .unwrap());
     };
     log::debug!("signature={}", signature);
@@ -226,17 +368,79 @@ async fn serve_req(
         }
     };
 
-    match triagebot::webhook(event, payload, &ctx).await {
-        Ok(true) => Ok(Response::new(Body::from("processed request"))),
-        Ok(false) => Ok(Response::new(Body::from("ignored request"))),
+    // Take a turn among the events about the same issue now, as the awaits
+    // below may let a later event overtake this one.
+    let ticket = issue_lock::reserve_payload(&payload);
+
+    // Keep the headers around so that the delivery can be inspected later.
+    let headers = req
+        .headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = value.to_str().ok()?;
+            Some((name.to_string(), serde_json::Value::from(value)))
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    // Store the delivery before responding, so that it is not lost if
+    // processing fails or takes longer than GitHub is willing to wait.
+    let inserted = db::webhooks::insert_delivery(
+        &*ctx.db.get().await,
+        &delivery_id,
+        &event.to_string(),
+        &serde_json::Value::Object(headers),
+        &payload,
+    )
+    .await;
+    match inserted {
+        Ok(true) => {}
+        Ok(false) => {
+            log::info!("ignoring duplicate delivery {}", delivery_id);
+            return Ok(Response::new(Body::from("ignored duplicate request")));
+        }
         Err(err) => {
             log::error!("request failed: {:?}", err);
-            Ok(Response::builder()
+            return Ok(Response::builder()
                 .status(StatusCode::INTERNAL_SERVER_ERROR)
                 .body(Body::from(format!("request failed: {:?}", err)))
-                .unwrap())
+                .unwrap());
         }
     }
+
+    task::spawn(
+        async move {
+            // Wait for earlier events about the same issue.
+            let _guard = match ticket {
+                Some(ticket) => Some(ticket.wait().await),
+                None => None,
+            };
+            db::process_new_webhook(&ctx, &*ctx.db.get().await, &delivery_id).await;
+        }
+        .in_current_span(),
+    );
+    Ok(Response::new(Body::from("queued request")))
+}
+
+/// Checks the `Authorization: Bearer <token>` header against the
+/// `TRIAGEBOT_ADMIN_TOKEN` environment variable.
+///
+/// Admin endpoints are disabled if the variable is not set.
+fn is_admin_request(headers: &header::HeaderMap) -> bool {
+    let Some(expected) = env::var("TRIAGEBOT_ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+    else {
+        return false;
+    };
+    let Some(token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    // `memcmp::eq` panics on differing lengths.
+    token.len() == expected.len() && openssl::memcmp::eq(token.as_bytes(), expected.as_bytes())
 }
 
 async fn run_server(addr: SocketAddr) -> anyhow::Result<()> {
@@ -246,11 +450,8 @@ async fn run_server(addr: SocketAddr) -> anyhow::Result<()> {
         .context("database migrations")?;
 
     let client = Client::new();
-    let gh = github::GithubClient::new_with_default_token(client.clone());
-    let oc = octocrab::OctocrabBuilder::new()
-        .personal_token(github::default_token_from_env())
-        .build()
-        .expect("Failed to build octograb.");
+    let gh = github::GithubClient::new_from_env(client.clone())?;
+    let oc = gh.octocrab()?;
     let ctx = Arc::new(Context {
         username: std::env::var("TRIAGEBOT_USERNAME").or_else(|err| match err {
             std::env::VarError::NotPresent => Ok("rustbot".to_owned()),
@@ -265,6 +466,8 @@ async fn run_server(addr: SocketAddr) -> anyhow::Result<()> {
         spawn_job_scheduler();
         spawn_job_runner(ctx.clone());
     }
+    spawn_webhook_retrier(ctx.clone());
+    triagebot::team_data::spawn_refresher(ctx.github.clone());
 
     let agenda = tower::ServiceBuilder::new()
         .buffer(10)
@@ -381,6 +584,41 @@ fn spawn_job_runner(ctx: Arc<Context>) {
     });
 }
 
+/// Spawns a background tokio task which runs continuously to retry webhook
+/// deliveries.
+///
+/// The retrier wakes up every `WEBHOOK_RETRY_CADENCE_IN_SECS` seconds to
+/// check for deliveries whose processing failed (and whose backoff has
+/// elapsed), or was interrupted.
+fn spawn_webhook_retrier(ctx: Arc<Context>) {
+    task::spawn(async move {
+        loop {
+            let ctx = ctx.clone();
+            let res = task::spawn(async move {
+                let pool = db::ClientPool::new();
+                let mut interval =
+                    time::interval(time::Duration::from_secs(WEBHOOK_RETRY_CADENCE_IN_SECS));
+
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = db::run_queued_webhooks(&ctx, &*pool.get().await).await {
+                        tracing::error!("run_queued_webhooks failed: {e:?}");
+                    }
+                }
+            });
+
+            match res.await {
+                Err(err) if err.is_panic() => {
+                    /* handle panic in above task, re-launching */
+                    tracing::error!("run_queued_webhooks task died (error={err})");
+                    tokio::time::sleep(std::time::Duration::new(5, 0)).await;
+                }
+                _ => unreachable!(),
+            }
+        }
+    });
+}
+
 /// Determines whether or not background scheduled jobs should be disabled for
 /// the purpose of testing.
 ///
@@ -0,0 +1,101 @@
+//! Prometheus metrics, served in the text exposition format from `/metrics`.
+//!
+//! All metrics are registered in the default `prometheus` registry.
+
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder,
+    HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+
+lazy_static! {
+    /// Webhook events received, by `X-GitHub-Event` name.
+    pub static ref WEBHOOK_EVENTS: IntCounterVec = register_int_counter_vec!(
+        "triagebot_webhook_events_total",
+        "Number of webhook events processed, by event name",
+        &["event"]
+    )
+    .unwrap();
+
+    /// Time spent in `handlers::handle`, by `X-GitHub-Event` name.
+    pub static ref HANDLE_DURATION: HistogramVec = register_histogram_vec!(
+        "triagebot_handle_duration_seconds",
+        "Time spent running the handlers for an event, by event name",
+        &["event"]
+    )
+    .unwrap();
+
+    /// Errors returned by handlers, by handler module.
+    pub static ref HANDLER_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "triagebot_handler_errors_total",
+        "Number of errors returned by handlers, by handler",
+        &["handler"]
+    )
+    .unwrap();
+
+    /// Panics caught in handlers and scheduled jobs, by handler module or
+    /// job name. Panicking handlers are counted in `HANDLER_ERRORS` as well.
+    pub static ref HANDLER_PANICS: IntCounterVec = register_int_counter_vec!(
+        "triagebot_handler_panics_total",
+        "Number of panics caught in handlers, by handler",
+        &["handler"]
+    )
+    .unwrap();
+
+    /// Scheduled jobs executed, by job name and result (`success` or `failure`).
+    pub static ref JOBS: IntCounterVec = register_int_counter_vec!(
+        "triagebot_jobs_total",
+        "Number of scheduled jobs executed, by job name and result",
+        &["job", "result"]
+    )
+    .unwrap();
+
+    /// Requests sent to the GitHub API, by method and response status.
+    pub static ref GITHUB_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "triagebot_github_requests_total",
+        "Number of requests sent to the GitHub API, by method and status",
+        &["method", "status"]
+    )
+    .unwrap();
+
+    /// Latency of requests sent to the GitHub API, by method.
+    pub static ref GITHUB_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "triagebot_github_request_duration_seconds",
+        "Latency of requests sent to the GitHub API, by method",
+        &["method"]
+    )
+    .unwrap();
+
+    /// The last seen `X-RateLimit-Remaining` value, by `X-RateLimit-Resource`.
+    pub static ref GITHUB_RATE_LIMIT_REMAINING: IntGaugeVec = register_int_gauge_vec!(
+        "triagebot_github_rate_limit_remaining",
+        "Remaining GitHub API requests in the current rate limit window, by resource",
+        &["resource"]
+    )
+    .unwrap();
+
+    /// When team data was last fetched successfully, by file (`teams.json`
+    /// or `zulip-map.json`).
+    pub static ref TEAM_DATA_REFRESHED: IntGaugeVec = register_int_gauge_vec!(
+        "triagebot_team_data_refreshed_timestamp_seconds",
+        "Unix time at which team data was last fetched, by file",
+        &["file"]
+    )
+    .unwrap();
+}
+
+/// Records an error returned by the given handler.
+pub fn handler_error(handler: &str) {
+    HANDLER_ERRORS.with_label_values(&[handler]).inc();
+}
+
+/// Renders all metrics in the Prometheus text exposition format.
+pub fn render() -> anyhow::Result<String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Records a panic caught in the given handler.
+pub fn handler_panic(handler: &str) {
+    HANDLER_PANICS.with_label_values(&[handler]).inc();
+}
@@ -1,6 +1,14 @@
 use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
 use std::fmt;
 
+/// Header containing the HMAC-SHA256 signature of the payload.
+pub const SIGNATURE_256_HEADER: &str = "X-Hub-Signature-256";
+
+/// Legacy header containing the HMAC-SHA1 signature of the payload.
+///
+/// This is only checked if `GITHUB_WEBHOOK_ALLOW_SHA1` is set.
+pub const SIGNATURE_SHA1_HEADER: &str = "X-Hub-Signature";
+
 #[derive(Debug)]
 pub struct SignedPayloadError;
 
@@ -12,8 +20,51 @@ impl fmt::Display for SignedPayloadError {
 
 impl std::error::Error for SignedPayloadError {}
 
+/// Whether or not the legacy `X-Hub-Signature` header may be used when the
+/// `X-Hub-Signature-256` header is missing.
+pub fn sha1_allowed() -> bool {
+    std::env::var_os("GITHUB_WEBHOOK_ALLOW_SHA1").is_some()
+}
+
+/// Returns the secrets which are accepted for webhook signatures.
+///
+/// `GITHUB_WEBHOOK_SECRET` may contain several comma-separated secrets. This
+/// allows rotating the secret by first adding the new one next to the old
+/// one, then updating the secret on GitHub, and finally removing the old one.
+fn webhook_secrets() -> Vec<String> {
+    let secrets = std::env::var("GITHUB_WEBHOOK_SECRET").expect("Missing GITHUB_WEBHOOK_SECRET");
+    secrets
+        .split(',')
+        .map(|secret| secret.trim())
+        .filter(|secret| !secret.is_empty())
+        .map(|secret| secret.to_string())
+        .collect()
+}
+
+/// Checks the signature from the `X-Hub-Signature-256` (or legacy
+/// `X-Hub-Signature`) header against the configured webhook secrets.
 pub fn assert_signed(signature: &str, payload: &[u8]) -> Result<(), SignedPayloadError> {
-    let signature = signature.get("sha1=".len()..).ok_or(SignedPayloadError)?;
+    verify_signature(signature, payload, &webhook_secrets(), sha1_allowed())
+}
+
+fn verify_signature<S: AsRef<[u8]>>(
+    signature: &str,
+    payload: &[u8],
+    secrets: &[S],
+    allow_sha1: bool,
+) -> Result<(), SignedPayloadError> {
+    let (digest, signature) = if let Some(signature) = signature.strip_prefix("sha256=") {
+        (MessageDigest::sha256(), signature)
+    } else if let Some(signature) = signature.strip_prefix("sha1=") {
+        if !allow_sha1 {
+            tracing::trace!("rejecting sha1 signature, GITHUB_WEBHOOK_ALLOW_SHA1 is not set");
+            return Err(SignedPayloadError);
+        }
+        (MessageDigest::sha1(), signature)
+    } else {
+        tracing::trace!("unknown signature format {:?}", signature);
+        return Err(SignedPayloadError);
+    };
     let signature = match hex::decode(&signature) {
         Ok(e) => e,
         Err(e) => {
@@ -22,18 +73,69 @@ pub fn assert_signed(signature: &str, payload: &[u8]) -> Result<(), SignedPayloa
         }
     };
 
-    let key = PKey::hmac(
-        std::env::var("GITHUB_WEBHOOK_SECRET")
-            .expect("Missing GITHUB_WEBHOOK_SECRET")
-            .as_bytes(),
-    )
-    .unwrap();
-    let mut signer = Signer::new(MessageDigest::sha1(), &key).unwrap();
-    signer.update(&payload).unwrap();
-    let hmac = signer.sign_to_vec().unwrap();
-
-    if !memcmp::eq(&hmac, &signature) {
-        return Err(SignedPayloadError);
+    for secret in secrets {
+        let key = PKey::hmac(secret.as_ref()).unwrap();
+        let mut signer = Signer::new(digest, &key).unwrap();
+        signer.update(&payload).unwrap();
+        let hmac = signer.sign_to_vec().unwrap();
+
+        // `memcmp::eq` panics on differing lengths.
+        if hmac.len() == signature.len() && memcmp::eq(&hmac, &signature) {
+            return Ok(());
+        }
+    }
+    Err(SignedPayloadError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example from https://docs.github.com/en/webhooks/using-webhooks/validating-webhook-deliveries
+    const SECRET: &str = "It's a Secret to Everybody";
+    const PAYLOAD: &[u8] = b"Hello, World!";
+    const SHA256: &str = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+    const SHA1: &str = "sha1=01dc10d0c83e72ed246219cdd91669667fe2ca59";
+
+    #[test]
+    fn sha256() {
+        assert!(verify_signature(SHA256, PAYLOAD, &[SECRET], false).is_ok());
+        assert!(verify_signature(SHA256, b"Hello, World?", &[SECRET], false).is_err());
+        assert!(verify_signature(SHA256, PAYLOAD, &["wrong secret"], false).is_err());
+    }
+
+    #[test]
+    fn sha1() {
+        assert!(verify_signature(SHA1, PAYLOAD, &[SECRET], true).is_ok());
+        assert!(verify_signature(SHA1, b"Hello, World?", &[SECRET], true).is_err());
+        // Only accepted when explicitly enabled.
+        assert!(verify_signature(SHA1, PAYLOAD, &[SECRET], false).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        for signature in [
+            "",
+            "sha256=",
+            "sha256=zz",
+            "sha256=757107",
+            "md5=757107ea0eb2509fc211221cce984b8a",
+            "757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+        ] {
+            assert!(verify_signature(signature, PAYLOAD, &[SECRET], true).is_err());
+        }
+    }
+
+    #[test]
+    fn rotation() {
+        // During rotation both the old and the new secret are accepted.
+        let secrets = ["new secret", SECRET];
+        assert!(verify_signature(SHA256, PAYLOAD, &secrets, false).is_ok());
+        let secrets = [SECRET, "new secret"];
+        assert!(verify_signature(SHA256, PAYLOAD, &secrets, false).is_ok());
+        // Once the old secret is removed, its signatures are rejected.
+        assert!(verify_signature(SHA256, PAYLOAD, &["new secret"], false).is_err());
+        let no_secrets: &[&str] = &[];
+        assert!(verify_signature(SHA256, PAYLOAD, no_secrets, false).is_err());
     }
-    Ok(())
 }
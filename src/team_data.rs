@@ -1,10 +1,92 @@
+//! Data about teams, from the rust-lang team API by default.
+//!
+//! The data can instead be loaded from another source, see [`Source`]. In all
+//! cases it has the shape of the team API's `teams.json` and `zulip-map.json`.
+//!
+//! The data is cached, and refreshed in the background by
+//! [`spawn_refresher`], so handlers can look it up as often as they like. If
+//! the team API can't be reached, the last data fetched successfully keeps
+//! being used.
+
 use crate::github::GithubClient;
 use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
 use rust_team_data::v1::{Teams, ZulipMapping, BASE_URL};
 use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing as log;
+
+/// How often the background refresher fetches the data.
+const REFRESH_EVERY: Duration = Duration::from_secs(4 * 60);
+/// Data older than this is fetched again when it is looked up, in case the
+/// background refresher isn't running.
+const MAX_AGE: Duration = Duration::from_secs(5 * 60);
+/// How long to keep using stale data before trying again, after fetching
+/// failed.
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(30);
+
+const TEAMS_FILE: &str = "teams.json";
+const ZULIP_MAP_FILE: &str = "zulip-map.json";
+
+static TEAMS: Lazy<Cache<Teams>> = Lazy::new(|| Cache::new(TEAMS_FILE));
+static ZULIP_MAP: Lazy<Cache<ZulipMapping>> = Lazy::new(|| Cache::new(ZULIP_MAP_FILE));
+
+/// Where the team data comes from.
+#[derive(Debug)]
+enum Source {
+    /// The team API, or a mirror of it, at the given base URL.
+    ///
+    /// This is the default, and the URL can be changed with `TEAM_API_URL`.
+    Url(String),
+    /// A directory containing `teams.json` and `zulip-map.json`, set with
+    /// `TEAM_DATA_DIR`.
+    Dir(PathBuf),
+    /// The teams of a GitHub organization, set with `TEAM_DATA_GITHUB_ORG`.
+    ///
+    /// GitHub doesn't know about Zulip, so the Zulip mapping is empty.
+    GithubOrg(String),
+}
+
+fn source() -> &'static Source {
+    static SOURCE: Lazy<Source> = Lazy::new(|| {
+        let source = if let Some(dir) = std::env::var_os("TEAM_DATA_DIR") {
+            Source::Dir(dir.into())
+        } else if let Ok(org) = std::env::var("TEAM_DATA_GITHUB_ORG") {
+            Source::GithubOrg(org)
+        } else {
+            Source::Url(crate::github::base_url_from_env("TEAM_API_URL", BASE_URL))
+        };
+        log::info!("loading team data from {source:?}");
+        source
+    });
+    &SOURCE
+}
 
-async fn by_url<T: DeserializeOwned>(client: &GithubClient, path: &str) -> anyhow::Result<T> {
-    let url = format!("{}{}", BASE_URL, path);
+/// Loads one of the team API's files from the configured source.
+async fn load<T: DeserializeOwned>(client: &GithubClient, file: &str) -> anyhow::Result<T> {
+    match source() {
+        Source::Url(base_url) => by_url(client, &format!("{base_url}/{file}")).await,
+        Source::Dir(dir) => {
+            let path = dir.join(file);
+            let contents = std::fs::read(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            serde_json::from_slice(&contents)
+                .with_context(|| format!("failed to parse {}", path.display()))
+        }
+        Source::GithubOrg(org) => {
+            let value = match file {
+                TEAMS_FILE => github_org_teams(client, org).await?,
+                _ => serde_json::json!({ "users": {} }),
+            };
+            Ok(serde_json::from_value(value)?)
+        }
+    }
+}
+
+async fn by_url<T: DeserializeOwned>(client: &GithubClient, url: &str) -> anyhow::Result<T> {
     for _ in 0i32..3 {
         let map: Result<T, _> = client.json(client.raw().get(&url)).await;
         match map {
@@ -24,14 +106,251 @@ async fn by_url<T: DeserializeOwned>(client: &GithubClient, path: &str) -> anyho
     Err(anyhow::anyhow!("Failed to retrieve {} in 3 requests", url))
 }
 
-pub async fn zulip_map(client: &GithubClient) -> anyhow::Result<ZulipMapping> {
-    by_url(client, "/zulip-map.json")
-        .await
-        .context("team-api: zulip-map.json")
+/// One of the team data files.
+struct Cache<T> {
+    file: &'static str,
+    entry: Mutex<Option<Entry<T>>>,
+    /// Held while fetching, so that concurrent lookups share a single request.
+    fetching: tokio::sync::Mutex<()>,
+}
+
+struct Entry<T> {
+    value: Arc<T>,
+    /// When the data was last fetched successfully.
+    refreshed_at: DateTime<Utc>,
+    /// When the data should be fetched again on lookup.
+    expires_at: Instant,
+}
+
+impl<T: DeserializeOwned> Cache<T> {
+    fn new(file: &'static str) -> Cache<T> {
+        Cache {
+            file,
+            entry: Mutex::new(None),
+            fetching: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Returns the cached data if it hasn't expired.
+    fn fresh(&self) -> Option<Arc<T>> {
+        let entry = self.entry.lock().unwrap();
+        entry
+            .as_ref()
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.value.clone())
+    }
+
+    async fn get(&self, client: &GithubClient) -> anyhow::Result<Arc<T>> {
+        if let Some(value) = self.fresh() {
+            return Ok(value);
+        }
+        let _fetching = self.fetching.lock().await;
+        // Someone else may have fetched it while we were waiting.
+        if let Some(value) = self.fresh() {
+            return Ok(value);
+        }
+        self.fetch(client).await
+    }
+
+    async fn refresh(&self, client: &GithubClient) -> anyhow::Result<Arc<T>> {
+        let _fetching = self.fetching.lock().await;
+        self.fetch(client).await
+    }
+
+    /// Fetches the data, falling back to the stale data if that fails.
+    async fn fetch(&self, client: &GithubClient) -> anyhow::Result<Arc<T>> {
+        let result = load(client, self.file)
+            .await
+            .with_context(|| format!("team-api: {}", self.file));
+        let mut entry = self.entry.lock().unwrap();
+        match result {
+            Ok(value) => {
+                let value = Arc::new(value);
+                let now = Utc::now();
+                *entry = Some(Entry {
+                    value: value.clone(),
+                    refreshed_at: now,
+                    expires_at: Instant::now() + MAX_AGE,
+                });
+                crate::metrics::TEAM_DATA_REFRESHED
+                    .with_label_values(&[self.file])
+                    .set(now.timestamp());
+                Ok(value)
+            }
+            Err(e) => {
+                let Some(entry) = entry.as_mut() else {
+                    return Err(e);
+                };
+                log::warn!(
+                    "using team data from {} since refreshing it failed: {e:?}",
+                    entry.refreshed_at
+                );
+                entry.expires_at = Instant::now() + RETRY_AFTER_FAILURE;
+                Ok(entry.value.clone())
+            }
+        }
+    }
+}
+
+pub async fn zulip_map(client: &GithubClient) -> anyhow::Result<Arc<ZulipMapping>> {
+    ZULIP_MAP.get(client).await
+}
+
+pub async fn teams(client: &GithubClient) -> anyhow::Result<Arc<Teams>> {
+    TEAMS.get(client).await
+}
+
+#[derive(serde::Deserialize)]
+struct GithubTeam {
+    slug: String,
+    parent: Option<GithubTeamParent>,
+}
+
+#[derive(serde::Deserialize)]
+struct GithubTeamParent {
+    slug: String,
+}
+
+#[derive(serde::Deserialize)]
+struct GithubMember {
+    login: String,
+    id: u64,
+}
+
+/// Fetches the teams of a GitHub organization and their members, in the
+/// shape of `teams.json`.
+async fn github_org_teams(client: &GithubClient, org: &str) -> anyhow::Result<serde_json::Value> {
+    let api_url = crate::github::api_url();
+    let teams: Vec<GithubTeam> =
+        github_pages(client, &format!("{api_url}/orgs/{org}/teams")).await?;
+    let mut members = Vec::new();
+    for team in teams {
+        let url = format!("{api_url}/orgs/{org}/teams/{}/members", team.slug);
+        let team_members: Vec<GithubMember> = github_pages(client, &url).await?;
+        members.push((team, team_members));
+    }
+    Ok(teams_json(members))
+}
+
+/// Fetches all pages of a GitHub API list.
+async fn github_pages<T: DeserializeOwned>(
+    client: &GithubClient,
+    url: &str,
+) -> anyhow::Result<Vec<T>> {
+    let mut items = Vec::new();
+    for page in 1.. {
+        let new: Vec<T> = client
+            .json(client.get(&format!("{url}?per_page=100&page={page}")))
+            .await?;
+        if new.is_empty() {
+            break;
+        }
+        items.extend(new);
+    }
+    Ok(items)
+}
+
+/// Converts GitHub teams into `teams.json`.
+///
+/// Handlers expect an `all` team with everyone in it, so one is added with
+/// the members of all the teams.
+fn teams_json(teams: Vec<(GithubTeam, Vec<GithubMember>)>) -> serde_json::Value {
+    let member = |m: &GithubMember| {
+        serde_json::json!({
+            "name": m.login,
+            "github": m.login,
+            "github_id": m.id,
+            "is_lead": false,
+        })
+    };
+    let team = |name: &str, parent: Option<&str>, members: Vec<serde_json::Value>| {
+        serde_json::json!({
+            "name": name,
+            "kind": "team",
+            "subteam_of": parent,
+            "members": members,
+            "alumni": [],
+            "discord": [],
+        })
+    };
+
+    let mut json = serde_json::Map::new();
+    let mut everyone: Vec<&GithubMember> = Vec::new();
+    for (t, members) in &teams {
+        for m in members {
+            if !everyone.iter().any(|e| e.id == m.id) {
+                everyone.push(m);
+            }
+        }
+        let parent = t.parent.as_ref().map(|p| p.slug.as_str());
+        json.insert(
+            t.slug.clone(),
+            team(&t.slug, parent, members.iter().map(member).collect()),
+        );
+    }
+    json.entry("all")
+        .or_insert_with(|| team("all", None, everyone.into_iter().map(member).collect()));
+    serde_json::Value::Object(json)
+}
+
+/// Spawns a background task which keeps the cached team data up to date.
+pub fn spawn_refresher(client: GithubClient) {
+    tokio::task::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_EVERY);
+        loop {
+            interval.tick().await;
+            if let Err(e) = TEAMS.refresh(&client).await {
+                log::error!("failed to refresh team data: {e:?}");
+            }
+            if let Err(e) = ZULIP_MAP.refresh(&client).await {
+                log::error!("failed to refresh team data: {e:?}");
+            }
+        }
+    });
 }
 
-pub async fn teams(client: &GithubClient) -> anyhow::Result<Teams> {
-    by_url(client, "/teams.json")
-        .await
-        .context("team-api: teams.json")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn github_teams() {
+        let member = |login: &str, id| GithubMember {
+            login: login.to_string(),
+            id,
+        };
+        let teams = vec![
+            (
+                GithubTeam {
+                    slug: "compiler".to_string(),
+                    parent: None,
+                },
+                vec![member("alice", 1), member("bob", 2)],
+            ),
+            (
+                GithubTeam {
+                    slug: "compiler-contributors".to_string(),
+                    parent: Some(GithubTeamParent {
+                        slug: "compiler".to_string(),
+                    }),
+                },
+                vec![member("bob", 2), member("carol", 3)],
+            ),
+        ];
+        let teams: Teams = serde_json::from_value(teams_json(teams)).unwrap();
+        let logins = |name: &str| -> Vec<&str> {
+            teams.teams[name]
+                .members
+                .iter()
+                .map(|m| m.github.as_str())
+                .collect()
+        };
+        assert_eq!(logins("compiler"), ["alice", "bob"]);
+        assert_eq!(logins("compiler-contributors"), ["bob", "carol"]);
+        assert_eq!(logins("all"), ["alice", "bob", "carol"]);
+        assert_eq!(
+            teams.teams["compiler-contributors"].subteam_of.as_deref(),
+            Some("compiler")
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use crate::github::retry;
 use crate::handlers::Context;
 use chrono::{Duration, Utc};
 use hyper::{Body, Response, StatusCode};
@@ -23,14 +24,16 @@ pub async fn pulls(
     repo: &str,
 ) -> Result<Response<Body>, hyper::Error> {
     let octocrab = &ctx.octocrab;
-    let res = octocrab
-        .pulls(owner, repo)
-        .list()
-        .sort(octocrab::params::pulls::Sort::Updated)
-        .direction(octocrab::params::Direction::Ascending)
-        .per_page(100)
-        .send()
-        .await;
+    let res = retry::octocrab(true, || {
+        octocrab
+            .pulls(owner, repo)
+            .list()
+            .sort(octocrab::params::pulls::Sort::Updated)
+            .direction(octocrab::params::Direction::Ascending)
+            .per_page(100)
+            .send()
+    })
+    .await;
     let mut page = match res {
         Ok(page) => page,
         Err(_) => {
@@ -42,10 +45,11 @@ pub async fn pulls(
     };
     let mut base_pulls = page.take_items();
     let mut next_page = page.next;
-    while let Some(mut page) = octocrab
-        .get_page::<octocrab::models::pulls::PullRequest>(&next_page)
-        .await
-        .unwrap()
+    while let Some(mut page) = retry::octocrab(true, || {
+        octocrab.get_page::<octocrab::models::pulls::PullRequest>(&next_page)
+    })
+    .await
+    .unwrap()
     {
         base_pulls.extend(page.take_items());
         next_page = page.next;
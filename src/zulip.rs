@@ -4,6 +4,7 @@ use crate::github::{self, GithubClient};
 use crate::handlers::docs_update::docs_update;
 use crate::handlers::Context;
 use anyhow::{format_err, Context as _};
+use once_cell::sync::Lazy;
 use std::convert::TryInto;
 use std::env;
 use std::fmt::Write as _;
@@ -69,7 +70,28 @@ struct Response {
     content: String,
 }
 
-pub const BOT_EMAIL: &str = "triage-rust-lang-bot@zulipchat.com";
+/// The base URL of the Zulip server.
+///
+/// This can be changed with `ZULIP_URL`.
+pub fn zulip_url() -> &'static str {
+    static URL: Lazy<String> =
+        Lazy::new(|| github::base_url_from_env("ZULIP_URL", "https://rust-lang.zulipchat.com"));
+    &URL
+}
+
+/// The email address of the bot's Zulip account, used together with
+/// `ZULIP_API_TOKEN`.
+///
+/// This can be changed with `ZULIP_BOT_EMAIL`.
+pub fn bot_email() -> &'static str {
+    static EMAIL: Lazy<String> = Lazy::new(|| {
+        env::var("ZULIP_BOT_EMAIL")
+            .ok()
+            .filter(|email| !email.is_empty())
+            .unwrap_or_else(|| "triage-rust-lang-bot@zulipchat.com".to_string())
+    });
+    &EMAIL
+}
 
 pub async fn to_github_id(client: &GithubClient, zulip_id: usize) -> anyhow::Result<Option<i64>> {
     let map = crate::team_data::zulip_map(client).await?;
@@ -186,7 +208,7 @@ fn handle_command<'a>(
                                 .await
                                 .map_err(|e| format_err!("Failed to await at this time: {e:?}"))
                             }
-                            Some("docs-update") => return trigger_docs_update(message_data),
+                            Some("docs-update") => return trigger_docs_update(&ctx, message_data),
                             _ => {}
                         }
                     }
@@ -240,8 +262,8 @@ async fn execute_for_other_user(
     let members = ctx
         .github
         .raw()
-        .get("https://rust-lang.zulipchat.com/api/v1/users")
-        .basic_auth(BOT_EMAIL, Some(&bot_api_token))
+        .get(format!("{}/api/v1/users", zulip_url()))
+        .basic_auth(bot_email(), Some(&bot_api_token))
         .send()
         .await
         .map_err(|e| format_err!("Failed to get list of zulip users: {e:?}."))?;
@@ -278,7 +300,7 @@ async fn execute_for_other_user(
         },
         content: &message,
     }
-    .send(ctx.github.raw())
+    .send(&ctx.github)
     .await;
 
     match res {
@@ -355,7 +377,7 @@ impl Recipient<'_> {
     }
 
     pub fn url(&self) -> String {
-        format!("https://rust-lang.zulipchat.com/#narrow/{}", self.narrow())
+        format!("{}/#narrow/{}", zulip_url(), self.narrow())
     }
 }
 
@@ -386,6 +408,25 @@ fn test_encode() {
     check_encode("áé…", ".C3.A1.C3.A9.E2.80.A6");
 }
 
+/// Sends a request to the Zulip API, or only records it if `client` is in
+/// dry-run mode.
+///
+/// Zulip requests are made with the GitHub client's HTTP client, so that
+/// they follow its dry-run setting.
+async fn send_request(
+    client: &GithubClient,
+    req: reqwest::RequestBuilder,
+) -> anyhow::Result<reqwest::Response> {
+    let req = req.build()?;
+    if client.is_dry_run() {
+        crate::dry_run::record("zulip", &req);
+        // Pretend a message with ID 0 was sent.
+        let body = r#"{"result":"success","msg":"","id":0}"#;
+        return Ok(hyper::Response::new(body).into());
+    }
+    Ok(client.raw().execute(req).await?)
+}
+
 #[derive(serde::Serialize)]
 pub struct MessageApiRequest<'a> {
     pub recipient: Recipient<'a>,
@@ -397,7 +438,7 @@ impl<'a> MessageApiRequest<'a> {
         self.recipient.url()
     }
 
-    pub async fn send(&self, client: &reqwest::Client) -> anyhow::Result<reqwest::Response> {
+    pub async fn send(&self, client: &GithubClient) -> anyhow::Result<reqwest::Response> {
         let bot_api_token = env::var("ZULIP_API_TOKEN").expect("ZULIP_API_TOKEN");
 
         #[derive(serde::Serialize)]
@@ -410,9 +451,10 @@ impl<'a> MessageApiRequest<'a> {
             content: &'a str,
         }
 
-        Ok(client
-            .post("https://rust-lang.zulipchat.com/api/v1/messages")
-            .basic_auth(BOT_EMAIL, Some(&bot_api_token))
+        let req = client
+            .raw()
+            .post(format!("{}/api/v1/messages", zulip_url()))
+            .basic_auth(bot_email(), Some(&bot_api_token))
             .form(&SerializedApi {
                 type_: match self.recipient {
                     Recipient::Stream { .. } => "stream",
@@ -427,9 +469,8 @@ impl<'a> MessageApiRequest<'a> {
                     Recipient::Private { .. } => None,
                 },
                 content: self.content,
-            })
-            .send()
-            .await?)
+            });
+        send_request(client, req).await
     }
 }
 
@@ -448,7 +489,7 @@ pub struct UpdateMessageApiRequest<'a> {
 }
 
 impl<'a> UpdateMessageApiRequest<'a> {
-    pub async fn send(&self, client: &reqwest::Client) -> anyhow::Result<reqwest::Response> {
+    pub async fn send(&self, client: &GithubClient) -> anyhow::Result<reqwest::Response> {
         let bot_api_token = env::var("ZULIP_API_TOKEN").expect("ZULIP_API_TOKEN");
 
         #[derive(serde::Serialize)]
@@ -461,19 +502,20 @@ impl<'a> UpdateMessageApiRequest<'a> {
             pub content: Option<&'a str>,
         }
 
-        Ok(client
+        let req = client
+            .raw()
             .patch(&format!(
-                "https://rust-lang.zulipchat.com/api/v1/messages/{}",
+                "{}/api/v1/messages/{}",
+                zulip_url(),
                 self.message_id
             ))
-            .basic_auth(BOT_EMAIL, Some(&bot_api_token))
+            .basic_auth(bot_email(), Some(&bot_api_token))
             .form(&SerializedApi {
                 topic: self.topic,
                 propagate_mode: self.propagate_mode,
                 content: self.content,
-            })
-            .send()
-            .await?)
+            });
+        send_request(client, req).await
     }
 }
 
@@ -651,18 +693,19 @@ struct AddReaction<'a> {
 }
 
 impl<'a> AddReaction<'a> {
-    pub async fn send(self, client: &reqwest::Client) -> anyhow::Result<reqwest::Response> {
+    pub async fn send(self, client: &GithubClient) -> anyhow::Result<reqwest::Response> {
         let bot_api_token = env::var("ZULIP_API_TOKEN").expect("ZULIP_API_TOKEN");
 
-        Ok(client
+        let req = client
+            .raw()
             .post(&format!(
-                "https://rust-lang.zulipchat.com/api/v1/messages/{}/reactions",
+                "{}/api/v1/messages/{}/reactions",
+                zulip_url(),
                 self.message_id
             ))
-            .basic_auth(BOT_EMAIL, Some(&bot_api_token))
-            .form(&self)
-            .send()
-            .await?)
+            .basic_auth(bot_email(), Some(&bot_api_token))
+            .form(&self);
+        send_request(client, req).await
     }
 }
 
@@ -715,7 +758,7 @@ async fn post_waiter(
         },
         content: waiting.primary,
     }
-    .send(ctx.github.raw())
+    .send(&ctx.github)
     .await?;
     let body = posted.text().await?;
     let message_id = serde_json::from_str::<SentMessage>(&body)
@@ -727,7 +770,7 @@ async fn post_waiter(
             message_id,
             emoji_name: reaction,
         }
-        .send(&ctx.github.raw())
+        .send(&ctx.github)
         .await
         .context("emoji reaction failed")?;
     }
@@ -735,8 +778,9 @@ async fn post_waiter(
     Ok(None)
 }
 
-fn trigger_docs_update(message: &Message) -> anyhow::Result<Option<String>> {
+fn trigger_docs_update(ctx: &Context, message: &Message) -> anyhow::Result<Option<String>> {
     let message = message.clone();
+    let github = ctx.github.clone();
     // The default Zulip timeout of 10 seconds can be too short, so process in
     // the background.
     tokio::task::spawn(async move {
@@ -754,7 +798,7 @@ fn trigger_docs_update(message: &Message) -> anyhow::Result<Option<String>> {
             recipient,
             content: &response,
         };
-        if let Err(e) = message.send(&reqwest::Client::new()).await {
+        if let Err(e) = message.send(&github).await {
             log::error!("failed to send Zulip response: {e:?}\nresponse was:\n{response}");
         }
     });
@@ -0,0 +1,314 @@
+//! Harness for end-to-end tests of the webhook handlers.
+//!
+//! The handlers run against an in-process fake GitHub server, which serves
+//! canned responses (mostly recorded JSON fixtures from `tests/fixtures`) and
+//! captures every request it receives, so tests can assert on the API calls
+//! made. The team API and raw file downloads (`triagebot.toml`) are served by
+//! the same server.
+//!
+//! The handlers also need a Postgres database, which is taken from
+//! `TRIAGEBOT_TEST_DATABASE_URL`. Tests are skipped if it is not set.
+//!
+//! Tests are run one at a time, as they share the fake server. Each test
+//! should use its own repository name, since the configuration is cached per
+//! repository.
+
+#![allow(dead_code)]
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Response, Server, StatusCode};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Mutex, MutexGuard};
+use triagebot::db::ClientPool;
+use triagebot::github::GithubClient;
+use triagebot::handlers::Context;
+use triagebot::{EventName, WebhookAttempt, WebhookError};
+
+/// A request captured by the fake server.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: Method,
+    /// The path, including the query string.
+    pub path: String,
+    pub body: String,
+}
+
+impl Request {
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body)
+            .unwrap_or_else(|e| panic!("request body of {self:?} is not JSON: {e}"))
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    /// Responses by method and path (including the query string, if any).
+    routes: HashMap<(Method, String), (StatusCode, String)>,
+    requests: Vec<Request>,
+}
+
+static STATE: Lazy<Mutex<MockState>> = Lazy::new(Default::default);
+
+/// Serializes the tests, since they share the fake server and the
+/// environment.
+static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+/// The base URL of the fake server.
+///
+/// Starting the server points triagebot at it, so this must be forced before
+/// anything reads the URL configuration.
+static SERVER_URL: Lazy<String> = Lazy::new(|| {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    // The server gets its own runtime, since each test has its own runtime
+    // which only lives as long as the test.
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let make_svc = make_service_fn(|_conn| async {
+                Ok::<_, Infallible>(service_fn(|req| async move {
+                    Ok::<_, Infallible>(serve(req).await)
+                }))
+            });
+            Server::from_tcp(listener)
+                .unwrap()
+                .serve(make_svc)
+                .await
+                .unwrap();
+        });
+    });
+
+    std::env::set_var("GITHUB_API_URL", &url);
+    std::env::set_var("GITHUB_RAW_URL", format!("{url}/raw"));
+    std::env::set_var("TEAM_API_URL", format!("{url}/team"));
+    std::env::set_var("ZULIP_URL", format!("{url}/zulip"));
+    url
+});
+
+async fn serve(req: hyper::Request<Body>) -> Response<Body> {
+    let method = req.method().clone();
+    let path = req
+        .uri()
+        .path_and_query()
+        .map_or_else(|| req.uri().path().to_string(), |p| p.to_string());
+    let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+    let body = String::from_utf8_lossy(&body).into_owned();
+
+    let mut state = STATE.lock().unwrap();
+    state.requests.push(Request {
+        method: method.clone(),
+        path: path.clone(),
+        body,
+    });
+    let without_query = path.split('?').next().unwrap().to_string();
+    let (status, body) = state
+        .routes
+        .get(&(method.clone(), path))
+        .or_else(|| state.routes.get(&(method.clone(), without_query)))
+        .cloned()
+        .unwrap_or_else(|| {
+            if method == Method::GET {
+                (
+                    StatusCode::NOT_FOUND,
+                    r#"{"message": "Not Found"}"#.to_string(),
+                )
+            } else {
+                // Writes succeed unless a test says otherwise.
+                (StatusCode::OK, "{}".to_string())
+            }
+        });
+    Response::builder()
+        .status(status)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// Loads a JSON fixture from `tests/fixtures`.
+///
+/// `{{api}}` is replaced with the fake server's URL and `{{repo}}` with the
+/// given repository name.
+pub fn fixture(name: &str, repo: &str) -> serde_json::Value {
+    let contents = fixture_text(name, repo);
+    serde_json::from_str(&contents).unwrap_or_else(|e| panic!("invalid fixture {name}: {e}"))
+}
+
+/// Loads a fixture from `tests/fixtures` as text, with the same replacements
+/// as [`fixture`].
+pub fn fixture_text(name: &str, repo: &str) -> String {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("failed to read {path}: {e}"))
+        .replace("{{api}}", &SERVER_URL)
+        .replace("{{repo}}", repo)
+}
+
+pub struct TestContext {
+    pub ctx: Context,
+    pub repo: String,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl TestContext {
+    /// Sets up a test for the given repository, with the given
+    /// `triagebot.toml` contents.
+    ///
+    /// Returns `None` if no test database is configured, in which case the
+    /// test should be skipped. On CI, where the `CI` environment variable is
+    /// set, the database is required, so that the tests can't be skipped by
+    /// accident.
+    pub async fn new(repo: &str, config: &str) -> Option<TestContext> {
+        let guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let Ok(db_url) = std::env::var("TRIAGEBOT_TEST_DATABASE_URL") else {
+            if std::env::var_os("CI").is_some() {
+                panic!("TRIAGEBOT_TEST_DATABASE_URL must be set on CI");
+            }
+            eprintln!("skipping test, TRIAGEBOT_TEST_DATABASE_URL is not set");
+            return None;
+        };
+        std::env::set_var("DATABASE_URL", db_url);
+        Lazy::force(&SERVER_URL);
+
+        {
+            let mut state = STATE.lock().unwrap();
+            *state = MockState::default();
+            state.routes.insert(
+                (Method::GET, format!("/raw/{repo}/master/triagebot.toml")),
+                (StatusCode::OK, config.to_string()),
+            );
+            state.routes.insert(
+                (Method::GET, "/team/teams.json".to_string()),
+                (StatusCode::OK, fixture_text("teams.json", repo)),
+            );
+        }
+
+        let db = ClientPool::new();
+        {
+            let client = db.get().await;
+            triagebot::db::run_migrations(&client).await.unwrap();
+            // Forget what the handlers remembered about this repository in
+            // previous runs.
+            client
+                .execute("DELETE FROM issue_data WHERE repo = $1", &[&repo])
+                .await
+                .unwrap();
+        }
+
+        let github = GithubClient::new(reqwest::Client::new(), "test-token".to_string());
+        let ctx = Context {
+            octocrab: github.octocrab().unwrap(),
+            github,
+            db,
+            username: "rustbot".to_string(),
+        };
+        Some(TestContext {
+            ctx,
+            repo: repo.to_string(),
+            _guard: guard,
+        })
+    }
+
+    /// Responds to requests with the given method and path with a JSON body.
+    ///
+    /// If the path has no query string, it matches requests with any query
+    /// string (unless there is a more specific response).
+    pub fn mock(&self, method: Method, path: &str, body: serde_json::Value) {
+        self.mock_text(method, path, StatusCode::OK, &body.to_string());
+    }
+
+    pub fn mock_text(&self, method: Method, path: &str, status: StatusCode, body: &str) {
+        STATE
+            .lock()
+            .unwrap()
+            .routes
+            .insert((method, path.to_string()), (status, body.to_string()));
+    }
+
+    /// Makes the given label exist in the repository.
+    pub fn mock_label(&self, label: &str) {
+        self.mock(
+            Method::GET,
+            &format!("/repos/{}/labels/{label}", self.repo),
+            serde_json::json!({ "name": label }),
+        );
+    }
+
+    /// Feeds a webhook event to triagebot, returning the handler errors.
+    pub async fn send(&self, event: &str, payload: serde_json::Value) -> Vec<String> {
+        self.try_send(event, payload)
+            .await
+            .unwrap_or_else(|e| panic!("webhook failed: {e:?}"))
+    }
+
+    /// Feeds a webhook event to triagebot, returning the handler errors, or
+    /// the error which makes the delivery fail.
+    pub async fn try_send(
+        &self,
+        event: &str,
+        payload: serde_json::Value,
+    ) -> Result<Vec<String>, WebhookError> {
+        self.send_attempt(event, payload, WebhookAttempt::default())
+            .await
+    }
+
+    /// Feeds a webhook event to triagebot again, running only the given
+    /// handlers as when a failed delivery is retried.
+    pub async fn retry(
+        &self,
+        event: &str,
+        payload: serde_json::Value,
+        handlers: &[&str],
+    ) -> Vec<String> {
+        let attempt = WebhookAttempt {
+            handlers: Some(handlers.iter().map(|name| name.to_string()).collect()),
+            ..Default::default()
+        };
+        self.send_attempt(event, payload, attempt)
+            .await
+            .unwrap_or_else(|e| panic!("webhook failed: {e:?}"))
+    }
+
+    async fn send_attempt(
+        &self,
+        event: &str,
+        payload: serde_json::Value,
+        mut attempt: WebhookAttempt,
+    ) -> Result<Vec<String>, WebhookError> {
+        let event = match event.parse::<EventName>() {
+            Ok(event) => event,
+            Err(infallible) => match infallible {},
+        };
+        triagebot::webhook(event, payload.to_string(), &self.ctx, &mut attempt).await?;
+        Ok(attempt.handler_errors)
+    }
+
+    /// All requests received so far.
+    pub fn requests(&self) -> Vec<Request> {
+        STATE.lock().unwrap().requests.clone()
+    }
+
+    /// Requests received so far which would modify something on GitHub.
+    ///
+    /// This leaves out GraphQL queries.
+    pub fn writes(&self) -> Vec<Request> {
+        self.requests()
+            .into_iter()
+            .filter(|req| req.method != Method::GET && !req.path.starts_with("/graphql"))
+            .collect()
+    }
+
+    /// Returns the bodies of the comments posted on the given issue.
+    pub fn comments(&self, number: u64) -> Vec<String> {
+        let path = format!("/repos/{}/issues/{number}/comments", self.repo);
+        self.writes()
+            .into_iter()
+            .filter(|req| req.method == Method::POST && req.path == path)
+            .map(|req| req.json()["body"].as_str().unwrap().to_string())
+            .collect()
+    }
+}
@@ -0,0 +1,620 @@
+//! End-to-end tests which feed webhook payloads to triagebot and check the
+//! requests it sends to GitHub. See `common` for how these are run.
+
+mod common;
+
+use common::{fixture, fixture_text, Request, TestContext};
+use hyper::{Method, StatusCode};
+use serde_json::json;
+
+/// Returns the method and path of each request, for comparing the requests
+/// made against an expected list.
+fn summary(requests: &[Request]) -> Vec<(String, String)> {
+    requests
+        .iter()
+        .map(|req| (req.method.to_string(), req.path.clone()))
+        .collect()
+}
+
+fn expected(requests: &[(&str, String)]) -> Vec<(String, String)> {
+    requests
+        .iter()
+        .map(|(method, path)| (method.to_string(), path.clone()))
+        .collect()
+}
+
+fn comment_event(repo: &str, user: &str, body: &str) -> serde_json::Value {
+    let mut payload = fixture("issue_comment_created.json", repo);
+    payload["comment"]["body"] = json!(body);
+    payload["comment"]["user"]["login"] = json!(user);
+    payload
+}
+
+#[tokio::test]
+async fn assign_claim() {
+    let Some(test) = TestContext::new("rust-lang/assign-test", "[assign]\n").await else {
+        return;
+    };
+    let repo = &test.repo;
+    let mut assigned = fixture("issue.json", repo);
+    assigned["assignees"] = json!([{ "login": "contributor", "id": 1002 }]);
+    test.mock(
+        Method::POST,
+        &format!("/repos/{repo}/issues/1/assignees"),
+        assigned,
+    );
+
+    let errors = test
+        .send(
+            "issue_comment",
+            comment_event(repo, "contributor", "@rustbot claim"),
+        )
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let writes = test.writes();
+    assert_eq!(
+        summary(&writes),
+        expected(&[
+            ("PATCH", format!("/repos/{repo}/issues/1")),
+            ("POST", format!("/repos/{repo}/issues/1/assignees")),
+            ("DELETE", format!("/repos/{repo}/issues/1/assignees")),
+        ])
+    );
+    let body = writes[0].json()["body"].as_str().unwrap().to_string();
+    assert!(body.starts_with("Something is broken."), "{body}");
+    assert!(body.contains(r#"{"user":"contributor"}"#), "{body}");
+    assert_eq!(writes[1].json(), json!({ "assignees": ["contributor"] }));
+    assert_eq!(writes[2].json(), json!({ "assignees": [] }));
+}
+
+#[tokio::test]
+async fn assign_claim_dry_run() {
+    let config = "dry-run = true\n\n[assign]\n";
+    let Some(test) = TestContext::new("rust-lang/assign-dry-run-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+
+    let errors = test
+        .send(
+            "issue_comment",
+            comment_event(repo, "contributor", "@rustbot claim"),
+        )
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+
+    // `set_assignee` doesn't check the skipped response of the assignment.
+    assert!(test.writes().is_empty());
+    let actions: Vec<_> = triagebot::dry_run::actions()
+        .into_iter()
+        .filter(|a| a.url.contains(repo.as_str()))
+        .map(|a| a.method)
+        .collect();
+    assert_eq!(actions, ["PATCH", "POST", "DELETE"]);
+}
+
+#[tokio::test]
+async fn relabel_team_member() {
+    let config = "[relabel]\nallow-unauthenticated = [\"A-*\"]\n";
+    let Some(test) = TestContext::new("rust-lang/relabel-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock_label("T-compiler");
+
+    let mut payload = comment_event(
+        repo,
+        "team-member",
+        "@rustbot label +T-compiler -A-diagnostics",
+    );
+    payload["issue"]["labels"] = json!([{ "name": "A-diagnostics" }]);
+    let errors = test.send("issue_comment", payload).await;
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let writes = test.writes();
+    assert_eq!(
+        summary(&writes),
+        expected(&[
+            ("POST", format!("/repos/{repo}/issues/1/labels")),
+            (
+                "DELETE",
+                format!("/repos/{repo}/issues/1/labels/A-diagnostics")
+            ),
+        ])
+    );
+    assert_eq!(writes[0].json(), json!({ "labels": ["T-compiler"] }));
+}
+
+#[tokio::test]
+async fn relabel_dry_run() {
+    let config = "dry-run = true\n\n[relabel]\nallow-unauthenticated = [\"A-*\"]\n";
+    let Some(test) = TestContext::new("rust-lang/dry-run-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock_label("T-compiler");
+
+    let payload = comment_event(repo, "team-member", "@rustbot label +T-compiler");
+    let errors = test.send("issue_comment", payload).await;
+    assert!(errors.is_empty(), "{errors:?}");
+
+    // Nothing was changed, but the change was recorded.
+    assert!(test.writes().is_empty());
+    let actions: Vec<_> = triagebot::dry_run::actions()
+        .into_iter()
+        .filter(|a| a.url.contains(repo.as_str()))
+        .collect();
+    assert_eq!(actions.len(), 1, "{actions:?}");
+    assert_eq!(actions[0].method, "POST");
+    assert!(actions[0]
+        .url
+        .ends_with(&format!("/repos/{repo}/issues/1/labels")));
+    assert_eq!(
+        actions[0].body.as_deref(),
+        Some(r#"{"labels":["T-compiler"]}"#)
+    );
+}
+
+#[tokio::test]
+async fn retry_runs_only_failed_handlers() {
+    let config = "[assign]\n\n[relabel]\nallow-unauthenticated = [\"A-*\"]\n";
+    let Some(test) = TestContext::new("rust-lang/retry-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock_label("T-compiler");
+
+    let payload = comment_event(
+        repo,
+        "team-member",
+        "@rustbot claim\n\n@rustbot label +T-compiler\n\n@rustbot lable +A-diagnostics",
+    );
+    let errors = test.retry("issue_comment", payload, &["relabel"]).await;
+    // The suggestion for the misspelled command was made the first time.
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let writes = test.writes();
+    assert_eq!(
+        summary(&writes),
+        expected(&[("POST", format!("/repos/{repo}/issues/1/labels"))])
+    );
+}
+
+#[tokio::test]
+async fn config_fetch_failure_is_retried() {
+    let config = "[relabel]\nallow-unauthenticated = [\"A-*\"]\n";
+    let Some(test) = TestContext::new("rust-lang/config-failure-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock_label("T-compiler");
+    let config_path = format!("/raw/{repo}/master/triagebot.toml");
+    test.mock_text(
+        Method::GET,
+        &config_path,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "",
+    );
+
+    let payload = comment_event(repo, "team-member", "@rustbot label +T-compiler");
+    let err = test
+        .try_send("issue_comment", payload.clone())
+        .await
+        .unwrap_err();
+    assert!(!err.is_permanent(), "{err:?}");
+    assert!(test.writes().is_empty());
+
+    // The failure wasn't cached, so the retry sees the configuration.
+    test.mock_text(Method::GET, &config_path, StatusCode::OK, config);
+    let errors = test.send("issue_comment", payload).await;
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(
+        summary(&test.writes()),
+        expected(&[("POST", format!("/repos/{repo}/issues/1/labels"))])
+    );
+}
+
+#[tokio::test]
+async fn relabel_outsider() {
+    let config = "[relabel]\nallow-unauthenticated = [\"A-*\"]\n";
+    let Some(test) = TestContext::new("rust-lang/relabel-outsider-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock_label("A-diagnostics");
+    test.mock_label("T-compiler");
+
+    // Allowed by `allow-unauthenticated`.
+    let errors = test
+        .send(
+            "issue_comment",
+            comment_event(repo, "contributor", "@rustbot label +A-diagnostics"),
+        )
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+    let writes = test.writes();
+    assert_eq!(
+        summary(&writes),
+        expected(&[("POST", format!("/repos/{repo}/issues/1/labels"))])
+    );
+    assert_eq!(writes[0].json(), json!({ "labels": ["A-diagnostics"] }));
+
+    // Not allowed, so the labels are left alone and an error is posted.
+    let errors = test
+        .send(
+            "issue_comment",
+            comment_event(repo, "contributor", "@rustbot label +T-compiler"),
+        )
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+    let comments = test.comments(1);
+    assert_eq!(comments.len(), 1, "{comments:?}");
+    assert!(
+        comments[0].starts_with("**Error**: Label T-compiler can only be set by Rust team members"),
+        "{}",
+        comments[0]
+    );
+    assert_eq!(test.writes().len(), 2);
+}
+
+#[tokio::test]
+async fn autolabel_trigger_label() {
+    let config = "[autolabel.\"T-compiler\"]\ntrigger_labels = [\"A-diagnostics\"]\n";
+    let Some(test) = TestContext::new("rust-lang/autolabel-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock_label("T-compiler");
+
+    let errors = test
+        .send("issues", fixture("issues_labeled.json", repo))
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let writes = test.writes();
+    assert_eq!(
+        summary(&writes),
+        expected(&[("POST", format!("/repos/{repo}/issues/1/labels"))])
+    );
+    assert_eq!(writes[0].json(), json!({ "labels": ["T-compiler"] }));
+}
+
+#[tokio::test]
+async fn autolabel_unknown_label() {
+    let config = "[autolabel.\"T-compiler\"]\ntrigger_labels = [\"A-diagnostics\"]\n";
+    let Some(test) = TestContext::new("rust-lang/autolabel-unknown-test", config).await else {
+        return;
+    };
+
+    let errors = test
+        .send("issues", fixture("issues_labeled.json", &test.repo))
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+
+    // The label doesn't exist in the repository, so it can't be added.
+    let comments = test.comments(1);
+    assert_eq!(comments.len(), 1, "{comments:?}");
+    assert!(comments[0].contains("T-compiler"), "{}", comments[0]);
+    assert_eq!(test.writes().len(), 1);
+}
+
+#[tokio::test]
+async fn no_merges() {
+    let config = "[no-merges]\nlabels = [\"has-merge-commits\"]\n";
+    let Some(test) = TestContext::new("rust-lang/no-merges-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock_label("has-merge-commits");
+    test.mock(
+        Method::GET,
+        &format!("/repos/{repo}/pulls/2/commits?page=1&per_page=100"),
+        fixture("pull_request_commits.json", repo),
+    );
+    test.mock(
+        Method::GET,
+        &format!("/repos/{repo}/pulls/2/commits?page=2&per_page=100"),
+        json!([]),
+    );
+
+    let errors = test
+        .send("pull_request", fixture("pull_request_opened.json", repo))
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let writes = test.writes();
+    assert_eq!(
+        summary(&writes),
+        expected(&[
+            ("POST", format!("/repos/{repo}/issues/2/labels")),
+            ("POST", format!("/repos/{repo}/issues/2/comments")),
+        ])
+    );
+    assert_eq!(writes[0].json(), json!({ "labels": ["has-merge-commits"] }));
+    let comment = writes[1].json()["body"].as_str().unwrap().to_string();
+    assert!(comment.contains("no merge policy"), "{comment}");
+    assert!(
+        comment.contains("- 2222222222222222222222222222222222222222"),
+        "{comment}"
+    );
+    assert!(
+        !comment.contains("3333333333333333333333333333333333333333"),
+        "{comment}"
+    );
+
+    // Pushing again without new merge commits doesn't repeat the comment.
+    let mut payload = fixture("pull_request_opened.json", repo);
+    payload["action"] = json!("synchronize");
+    payload["pull_request"]["labels"] = json!([{ "name": "has-merge-commits" }]);
+    let errors = test.send("pull_request", payload).await;
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(test.writes().len(), 2);
+}
+
+#[tokio::test]
+async fn mentions() {
+    let config = "[mentions.\"src/tools/foo\"]\ncc = [\"@foo-maintainer\"]\n\n\
+                  [mentions.\"src/tools/bar\"]\ncc = [\"@bar-maintainer\"]\n";
+    let Some(test) = TestContext::new("rust-lang/mentions-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock_text(
+        Method::GET,
+        &format!(
+            "/repos/{repo}/compare/1111111111111111111111111111111111111111...\
+             2222222222222222222222222222222222222222"
+        ),
+        StatusCode::OK,
+        &fixture_text("pull_request.diff", repo),
+    );
+
+    let errors = test
+        .send("pull_request", fixture("pull_request_opened.json", repo))
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(
+        test.comments(2),
+        ["Some changes occurred in src/tools/foo\n\ncc @foo-maintainer"]
+    );
+
+    // The same paths are only mentioned once.
+    let mut payload = fixture("pull_request_opened.json", repo);
+    payload["action"] = json!("synchronize");
+    let errors = test.send("pull_request", payload).await;
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(test.writes().len(), 1);
+}
+
+#[tokio::test]
+async fn pull_request_diff_fetched_once() {
+    let config = "[mentions.\"src/tools/foo\"]\ncc = [\"@foo-maintainer\"]\n\n\
+                  [autolabel.\"A-foo\"]\ntrigger_files = [\"src/tools/foo\"]\n";
+    let Some(test) = TestContext::new("rust-lang/diff-once-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    let compare = format!(
+        "/repos/{repo}/compare/1111111111111111111111111111111111111111...\
+         2222222222222222222222222222222222222222"
+    );
+    test.mock_text(
+        Method::GET,
+        &compare,
+        StatusCode::OK,
+        &fixture_text("pull_request.diff", repo),
+    );
+    test.mock_label("A-foo");
+
+    let errors = test
+        .send("pull_request", fixture("pull_request_opened.json", repo))
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+    // Both handlers used the diff, but it was only requested once.
+    assert_eq!(test.comments(2).len(), 1);
+    assert_eq!(
+        test.requests().iter().filter(|r| r.path == compare).count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn validate_config() {
+    let Some(test) = TestContext::new("rust-lang/validate-config-test", "[note]\n").await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock(
+        Method::GET,
+        &format!("/repos/{repo}/pulls/2/files"),
+        json!([{ "sha": "abc", "filename": "triagebot.toml", "blob_url": "" }]),
+    );
+    let raw_path = "/raw/contributor/fork/2222222222222222222222222222222222222222/triagebot.toml";
+    test.mock_text(
+        Method::GET,
+        raw_path,
+        StatusCode::OK,
+        "[relabel]\nallow-unauthenticated = \"A-*\"\n",
+    );
+
+    let errors = test
+        .send("pull_request", fixture("pull_request_opened.json", repo))
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+    let comments = test.comments(2);
+    assert_eq!(comments.len(), 1, "{comments:?}");
+    assert!(
+        comments[0].starts_with(
+            "The `triagebot.toml` in this pull request has some problems:\n\n\
+             - The file could not be parsed: "
+        ),
+        "{}",
+        comments[0]
+    );
+
+    // Pushing again with the same problems doesn't repeat the comment.
+    let mut payload = fixture("pull_request_opened.json", repo);
+    payload["action"] = json!("synchronize");
+    test.send("pull_request", payload.clone()).await;
+    assert_eq!(test.comments(2).len(), 1);
+
+    // Once fixed, a comment says so.
+    test.mock_text(
+        Method::GET,
+        raw_path,
+        StatusCode::OK,
+        "[relabel]\nallow-unauthenticated = [\"A-*\"]\n",
+    );
+    test.send("pull_request", payload).await;
+    assert_eq!(
+        test.comments(2)[1],
+        "The problems with `triagebot.toml` have been fixed."
+    );
+}
+
+#[tokio::test]
+async fn validate_config_retried() {
+    let Some(test) = TestContext::new("rust-lang/validate-config-retry-test", "[note]\n").await
+    else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock(
+        Method::GET,
+        &format!("/repos/{repo}/pulls/2/files"),
+        json!([{ "sha": "abc", "filename": "triagebot.toml", "blob_url": "" }]),
+    );
+    let raw_path = "/raw/contributor/fork/2222222222222222222222222222222222222222/triagebot.toml";
+    test.mock_text(Method::GET, raw_path, StatusCode::INTERNAL_SERVER_ERROR, "");
+
+    let payload = fixture("pull_request_opened.json", repo);
+    let err = test
+        .try_send("pull_request", payload.clone())
+        .await
+        .unwrap_err();
+    assert!(!err.is_permanent(), "{err:?}");
+    assert!(test.comments(2).is_empty());
+
+    test.mock_text(Method::GET, raw_path, StatusCode::OK, "[relabel\n");
+    let errors = test
+        .retry("pull_request", payload, &["validate_config"])
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(test.comments(2).len(), 1);
+}
+
+#[tokio::test]
+async fn validate_config_org_error() {
+    let Some(test) = TestContext::new("rust-lang/validate-config-org-test", "[note]\n").await
+    else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock(
+        Method::GET,
+        &format!("/repos/{repo}/pulls/2/files"),
+        json!([{ "sha": "abc", "filename": "triagebot.toml", "blob_url": "" }]),
+    );
+    test.mock_text(
+        Method::GET,
+        "/raw/contributor/fork/2222222222222222222222222222222222222222/triagebot.toml",
+        StatusCode::OK,
+        "[note]\n",
+    );
+    test.mock_text(
+        Method::GET,
+        "/raw/rust-lang/.github/HEAD/triagebot.toml",
+        StatusCode::OK,
+        "[relabel\n",
+    );
+
+    let errors = test
+        .send("pull_request", fixture("pull_request_opened.json", repo))
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+    let comments = test.comments(2);
+    assert_eq!(comments.len(), 1, "{comments:?}");
+    assert!(
+        comments[0].starts_with(
+            "The `triagebot.toml` in this pull request has some problems:\n\n\
+             - Malformed organization-wide `triagebot.toml`"
+        ),
+        "{}",
+        comments[0]
+    );
+}
+
+#[tokio::test]
+async fn base_branch_config() {
+    let config = "base-branch-config = true\n";
+    let Some(test) = TestContext::new("rust-lang/base-branch-config-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+    test.mock_text(
+        Method::GET,
+        &format!("/raw/{repo}/beta/triagebot.toml"),
+        StatusCode::OK,
+        "[mentions.\"src/tools/foo\"]\ncc = [\"@foo-maintainer\"]\n",
+    );
+    test.mock_text(
+        Method::GET,
+        &format!(
+            "/repos/{repo}/compare/1111111111111111111111111111111111111111...\
+             2222222222222222222222222222222222222222"
+        ),
+        StatusCode::OK,
+        &fixture_text("pull_request.diff", repo),
+    );
+
+    // The default branch has no `[mentions]`.
+    let errors = test
+        .send("pull_request", fixture("pull_request_opened.json", repo))
+        .await;
+    assert!(errors.is_empty(), "{errors:?}");
+    assert!(test.comments(2).is_empty());
+
+    let mut payload = fixture("pull_request_opened.json", repo);
+    payload["pull_request"]["base"]["ref"] = json!("beta");
+    let errors = test.send("pull_request", payload).await;
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(
+        test.comments(2),
+        ["Some changes occurred in src/tools/foo\n\ncc @foo-maintainer"]
+    );
+}
+
+#[tokio::test]
+async fn unknown_command() {
+    let config = "[relabel]\nallow-unauthenticated = [\"A-*\"]\n";
+    let Some(test) = TestContext::new("rust-lang/unknown-command-test", config).await else {
+        return;
+    };
+    let repo = &test.repo;
+
+    let body = "@rustbot lable +A-diagnostics\n\n@rustbot asign @me\n\nthanks @rustbot!";
+    let errors = test
+        .send("issue_comment", comment_event(repo, "contributor", body))
+        .await;
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(
+        errors[0].contains("Did you mean `@rustbot label`?"),
+        "{}",
+        errors[0]
+    );
+    assert_eq!(test.comments(1).len(), 1);
+}
+
+#[tokio::test]
+async fn unknown_command_suggestions_disabled() {
+    let config = "command-suggestions = false\n\n[relabel]\nallow-unauthenticated = [\"A-*\"]\n";
+    let Some(test) = TestContext::new("rust-lang/unknown-command-off-test", config).await else {
+        return;
+    };
+
+    let payload = comment_event(&test.repo, "contributor", "@rustbot lable +A-diagnostics");
+    let errors = test.send("issue_comment", payload).await;
+    assert!(errors.is_empty(), "{errors:?}");
+    assert!(test.writes().is_empty());
+}